@@ -0,0 +1,97 @@
+#!/usr/bin/env python3
+# example exec check: queries a Source engine game server (A2S_INFO) and
+# verifies the map it is running and how many players are connected
+#
+# reads {ip, host, team, vm, service, params} as JSON on stdin and prints
+# {up, short_error, long_error, points} as JSON on stdout
+#
+# params:
+#   map          map the server must be running (optional)
+#   min_players  fewest connected players to be scored up (default: 0)
+#   port         query port (default: 27015)
+
+import json
+import socket
+import struct
+import sys
+
+QUERY = b"\xff\xff\xff\xffTSource Engine Query\x00"
+TIMEOUT = 5
+
+
+def result(up, short_error=None, long_error=None):
+    json.dump(
+        {
+            "up": up,
+            "short_error": short_error,
+            "long_error": long_error,
+            "points": None,
+        },
+        sys.stdout,
+    )
+    sys.exit(0)
+
+
+def read_string(data, offset):
+    end = data.index(b"\x00", offset)
+    return data[offset:end].decode(errors="replace"), end + 1
+
+
+def query(ip, port):
+    family = socket.AF_INET6 if ":" in ip else socket.AF_INET
+    with socket.socket(family, socket.SOCK_DGRAM) as sock:
+        sock.settimeout(TIMEOUT)
+        sock.sendto(QUERY, (ip, port))
+        data = sock.recv(4096)
+        # newer servers answer with a challenge that must be echoed back
+        if data[4:5] == b"A":
+            sock.sendto(QUERY + data[5:9], (ip, port))
+            data = sock.recv(4096)
+
+    if data[:5] != b"\xff\xff\xff\xffI":
+        raise ValueError("unexpected reply type {!r}".format(data[4:5]))
+
+    # header, protocol, then name, map, folder and game
+    offset = 6
+    _, offset = read_string(data, offset)
+    map_name, offset = read_string(data, offset)
+    _, offset = read_string(data, offset)
+    _, offset = read_string(data, offset)
+    # steam app id, then players and max players
+    _, players, _ = struct.unpack_from("<hBB", data, offset)
+    return map_name, players
+
+
+def main():
+    target = json.load(sys.stdin)
+    params = target.get("params", {})
+    port = int(params.get("port", 27015))
+
+    try:
+        map_name, players = query(target["ip"], port)
+    except socket.timeout:
+        result(False, "Connection timed out", "No reply to A2S_INFO")
+    except (OSError, ValueError, IndexError, struct.error) as e:
+        result(False, "Query failed", "A2S_INFO failed: {}".format(e))
+
+    expected_map = params.get("map")
+    if expected_map is not None and map_name != expected_map:
+        result(
+            False,
+            "Incorrect map",
+            "Expected map '{}', got '{}'".format(expected_map, map_name),
+        )
+
+    min_players = int(params.get("min_players", 0))
+    if players < min_players:
+        result(
+            False,
+            "Not enough players",
+            "Expected at least {} players, got {}".format(min_players, players),
+        )
+
+    result(True)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,11 @@
+CREATE TABLE engine_states (
+    id                  SERIAL PRIMARY KEY,
+    state               TEXT NOT NULL,
+
+    time_started        TIMESTAMPTZ,
+    -- milliseconds left on the clock as of `time_started` (started) or the pause (stopped)
+    time_remaining_ms   BIGINT,
+    time                TIMESTAMPTZ NOT NULL,
+    -- heartbeat from the running engine, used to detect downtime on restart
+    last_seen           TIMESTAMPTZ NOT NULL
+);
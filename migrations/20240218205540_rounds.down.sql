@@ -0,0 +1,4 @@
+ALTER TABLE sla_violations DROP COLUMN IF EXISTS round;
+ALTER TABLE team_snapshots DROP COLUMN IF EXISTS round;
+ALTER TABLE service_checks DROP COLUMN IF EXISTS round;
+DROP TABLE IF EXISTS rounds;
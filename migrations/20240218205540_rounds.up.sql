@@ -0,0 +1,11 @@
+CREATE TABLE rounds (
+    number          INT PRIMARY KEY,
+
+    time_started    TIMESTAMPTZ NOT NULL,
+    time_finished   TIMESTAMPTZ
+);
+
+-- rows recorded before rounds were introduced are left without one
+ALTER TABLE service_checks ADD COLUMN round INT REFERENCES rounds(number);
+ALTER TABLE team_snapshots ADD COLUMN round INT REFERENCES rounds(number);
+ALTER TABLE sla_violations ADD COLUMN round INT REFERENCES rounds(number);
@@ -0,0 +1,12 @@
+CREATE TABLE password_changes (
+    id              SERIAL PRIMARY KEY,
+    team            TEXT NOT NULL,
+    vm              TEXT NOT NULL,
+    service         TEXT NOT NULL,
+
+    username        TEXT NOT NULL,
+    password        TEXT NOT NULL,
+    time            TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX password_changes_latest ON password_changes(team, vm, service, username, time DESC);
@@ -0,0 +1 @@
+ALTER TABLE service_checks DROP COLUMN IF EXISTS attempts;
@@ -0,0 +1,2 @@
+-- checks recorded before retries were introduced made a single attempt
+ALTER TABLE service_checks ADD COLUMN attempts INT NOT NULL DEFAULT 1;
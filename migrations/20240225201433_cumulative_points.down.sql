@@ -0,0 +1 @@
+ALTER TABLE team_snapshots DROP COLUMN IF EXISTS total_points;
@@ -0,0 +1,11 @@
+-- running total of `points` for the team as of each snapshot
+ALTER TABLE team_snapshots ADD COLUMN total_points INT;
+
+UPDATE team_snapshots SET total_points = totals.total_points
+FROM (
+    SELECT id, SUM(points) OVER (PARTITION BY team ORDER BY time, id) AS total_points
+    FROM team_snapshots
+) totals
+WHERE team_snapshots.id = totals.id;
+
+ALTER TABLE team_snapshots ALTER COLUMN total_points SET NOT NULL;
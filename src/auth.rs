@@ -7,6 +7,7 @@ use std::convert::Infallible;
 #[derive(Clone)]
 pub struct User {
 	pub username: String,
+	pub admin: bool,
 	password: String,
 }
 
@@ -26,6 +27,7 @@ impl std::fmt::Debug for User {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.debug_struct("User")
 			.field("username", &self.username)
+			.field("admin", &self.admin)
 			.field("password", &"[redacted]")
 			.finish()
 	}
@@ -49,19 +51,37 @@ impl AuthnBackend for Config {
 		&self,
 		creds: Self::Credentials,
 	) -> Result<Option<Self::User>, Self::Error> {
+		if creds.username == self.web.admin_username {
+			return Ok((creds.password == self.web.admin_password).then(|| User {
+				username: creds.username,
+				admin: true,
+				password: self.web.admin_password.to_owned(),
+			}));
+		}
+
 		Ok(self
 			.teams
 			.get(&creds.username)
 			.filter(|user| user.password == creds.password)
 			.map(|user| User {
 				username: creds.username,
+				admin: false,
 				password: user.password.to_owned(),
 			}))
 	}
 
 	async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
+		if *user_id == self.web.admin_username {
+			return Ok(Some(User {
+				username: user_id.to_owned(),
+				admin: true,
+				password: self.web.admin_password.to_owned(),
+			}));
+		}
+
 		Ok(self.teams.get(user_id).map(|user| User {
 			username: user_id.to_owned(),
+			admin: false,
 			password: user.password.to_owned(),
 		}))
 	}
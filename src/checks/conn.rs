@@ -0,0 +1,259 @@
+use crate::checks::{check_bail, errors::check_error, CheckResult};
+use openssl::ssl::{
+	SslConnector, SslMethod, SslSession, SslSessionCacheMode, SslVerifyMode, SslVersion,
+};
+use std::{
+	net::{IpAddr, SocketAddr},
+	pin::Pin,
+	sync::{Arc, Mutex},
+};
+use tokio::{
+	io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+	net::TcpStream,
+};
+use tokio_openssl::SslStream;
+
+// line-oriented plaintext protocols (FTP, SMTP, POP3, IMAP) that may be
+// upgraded to TLS mid-session
+
+// longest line accepted from a server
+const MAX_LINE_LEN: u64 = 64 * 1024;
+// largest raw read accepted from a server, e.g. an IMAP literal
+const MAX_READ_LEN: usize = 16 * 1024 * 1024;
+
+pub trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+pub type BoxedStream = Box<dyn Stream>;
+
+// the command without its arguments, so credentials don't leak into the long
+// error of a failed command
+pub fn command_name(command: &str) -> &str {
+	command.split(' ').next().unwrap_or_default()
+}
+
+pub async fn connect(ip: IpAddr, port: u16) -> CheckResult<TcpStream> {
+	let addr = SocketAddr::new(ip, port);
+	TcpStream::connect(addr).await.map_err(|e| {
+		check_error!(
+			"Connection failed",
+			format!("Failed to connect to {}: {}", addr, e)
+		)
+	})
+}
+
+// TLS client state shared by a connection and any further connections it
+// opens, which resume its session; FTP servers commonly require this of data
+// channels (e.g. vsftpd's `require_ssl_reuse`)
+#[derive(Clone)]
+pub struct Tls {
+	connector: SslConnector,
+	// sessions the server has issued, oldest first
+	sessions: Arc<Mutex<Vec<SslSession>>>,
+}
+
+impl Tls {
+	// competition boxes serve self-signed certificates, so they are not verified
+	pub fn new() -> CheckResult<Self> {
+		let sessions: Arc<Mutex<Vec<SslSession>>> = Arc::default();
+		let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+		builder.set_verify(SslVerifyMode::NONE);
+		// TLS 1.3 servers issue sessions after the handshake, so they're
+		// captured as they arrive rather than read off the stream once connected
+		builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+		let issued = sessions.clone();
+		builder.set_new_session_callback(move |_, session| {
+			issued.lock().unwrap().push(session);
+		});
+
+		Ok(Self {
+			connector: builder.build(),
+			sessions,
+		})
+	}
+
+	pub async fn connect(
+		&self,
+		ip: IpAddr,
+		stream: impl Stream + 'static,
+	) -> CheckResult<BoxedStream> {
+		let mut ssl = self
+			.connector
+			.configure()?
+			.verify_hostname(false)
+			.into_ssl(&ip.to_string())?;
+		if let Some(session) = self.take_session() {
+			// SAFETY: the session was issued to a connection from the same
+			// connector, and so the same context
+			unsafe { ssl.set_session(&session)? };
+		}
+
+		let mut stream = SslStream::new(ssl, stream)?;
+		Pin::new(&mut stream).connect().await.map_err(|e| {
+			check_error!(
+				"TLS negotiation failed",
+				format!("TLS negotiation with {} failed: {}", ip, e)
+			)
+		})?;
+		Ok(Box::new(stream))
+	}
+
+	// TLS 1.3 tickets are single-use, so each is handed out once, whereas
+	// earlier sessions can be resumed any number of times
+	fn take_session(&self) -> Option<SslSession> {
+		let mut sessions = self.sessions.lock().unwrap();
+		match sessions.last() {
+			Some(session) if session.protocol_version() == SslVersion::TLS1_3 => sessions.pop(),
+			session => session.cloned(),
+		}
+	}
+}
+
+pub struct Conn {
+	ip: IpAddr,
+	// address of our end of the connection, e.g. for FTP active mode
+	pub local_ip: IpAddr,
+	// set once the connection is encrypted
+	pub tls: Option<Tls>,
+	reader: BufReader<BoxedStream>,
+}
+
+impl Conn {
+	pub async fn open(ip: IpAddr, port: u16, implicit_tls: bool) -> CheckResult<Self> {
+		let stream = connect(ip, port).await?;
+		let local_ip = stream.local_addr()?.ip();
+		let conn = Self {
+			ip,
+			local_ip,
+			tls: None,
+			reader: BufReader::new(Box::new(stream)),
+		};
+
+		if implicit_tls {
+			conn.upgrade().await
+		} else {
+			Ok(conn)
+		}
+	}
+
+	pub async fn upgrade(self) -> CheckResult<Self> {
+		let tls = Tls::new()?;
+		let stream = tls.connect(self.ip, self.reader.into_inner()).await?;
+		Ok(Self {
+			ip: self.ip,
+			local_ip: self.local_ip,
+			tls: Some(tls),
+			reader: BufReader::new(stream),
+		})
+	}
+
+	pub async fn send_line(&mut self, line: &str) -> CheckResult {
+		let stream = self.reader.get_mut();
+		stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+		stream.flush().await?;
+		Ok(())
+	}
+
+	pub async fn read_line(&mut self) -> CheckResult<String> {
+		let mut line = String::new();
+		let read = (&mut self.reader)
+			.take(MAX_LINE_LEN)
+			.read_line(&mut line)
+			.await?;
+		if read == 0 {
+			check_bail!(
+				"Connection closed",
+				format!("{} closed the connection unexpectedly", self.ip)
+			);
+		}
+		if !line.ends_with('\n') && read as u64 == MAX_LINE_LEN {
+			check_bail!(
+				"Protocol error",
+				format!("{} sent a line longer than {} bytes", self.ip, MAX_LINE_LEN)
+			);
+		}
+		Ok(line.trim_end_matches(['\r', '\n']).to_owned())
+	}
+
+	// reads raw bytes, e.g. an IMAP literal
+	pub async fn read_exact(&mut self, len: usize) -> CheckResult<Vec<u8>> {
+		if len > MAX_READ_LEN {
+			check_bail!(
+				"Protocol error",
+				format!(
+					"{} announced {} bytes, more than the {} allowed",
+					self.ip, len, MAX_READ_LEN
+				)
+			);
+		}
+		let mut buf = vec![0; len];
+		self.reader.read_exact(&mut buf).await.map_err(|e| {
+			check_error!(
+				"Connection closed",
+				format!("Failed to read {} bytes from {}: {}", len, self.ip, e)
+			)
+		})?;
+		Ok(buf)
+	}
+
+	// reads a (possibly multi-line) reply with a three digit status code, as
+	// used by FTP and SMTP
+	pub async fn read_reply(&mut self) -> CheckResult<(u16, String)> {
+		let first = self.read_line().await?;
+		let Some(code) = first.get(..3).and_then(|code| code.parse::<u16>().ok()) else {
+			check_bail!(
+				"Protocol error",
+				format!("Malformed reply from {}: '{}'", self.ip, first)
+			);
+		};
+
+		let multiline = first.as_bytes().get(3) == Some(&b'-');
+		let mut text = vec![first];
+		if multiline {
+			let terminator = format!("{} ", code);
+			loop {
+				let line = self.read_line().await?;
+				let last = line.starts_with(&terminator) || line == terminator.trim_end();
+				text.push(line);
+				if last {
+					break;
+				}
+			}
+		}
+
+		Ok((code, text.join("\n")))
+	}
+
+	// sends a command and reads its reply, failing with `short` unless the
+	// status code is one of `expected`
+	pub async fn command(
+		&mut self,
+		command: &str,
+		expected: &[u16],
+		short: &'static str,
+	) -> CheckResult<String> {
+		self.send_line(command).await?;
+		self.expect_reply(command, expected, short).await
+	}
+
+	pub async fn expect_reply(
+		&mut self,
+		command: &str,
+		expected: &[u16],
+		short: &'static str,
+	) -> CheckResult<String> {
+		let (code, text) = self.read_reply().await?;
+		if !expected.contains(&code) {
+			check_bail!(
+				short,
+				format!(
+					"'{}' failed: expected {:?}, got '{}'",
+					command_name(command),
+					expected,
+					text
+				)
+			);
+		}
+		Ok(text)
+	}
+}
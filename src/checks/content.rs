@@ -0,0 +1,89 @@
+use crate::{
+	checks::{check_bail, CheckResult},
+	config::check_types::ContentMatch,
+};
+use rand::{distributions::Alphanumeric, Rng};
+
+impl ContentMatch {
+	pub fn verify(&self, content: &str) -> CheckResult {
+		if let Some(contains) = &self.contains {
+			if !content.contains(contains) {
+				check_bail!(
+					"Content mismatch",
+					format!(
+						"Content mismatch: expected '{}', got '{}'",
+						contains, content
+					)
+				);
+			}
+		}
+
+		if let Some(contains_regex) = &self.contains_regex {
+			if !contains_regex.is_match(content) {
+				check_bail!(
+					"Regex mismatch",
+					format!(
+						"Regex mismatch: expected match for '{}', got '{}'",
+						contains_regex, content
+					)
+				);
+			}
+		}
+
+		if let Some(equals) = &self.equals {
+			let trim = |s: &str| s.trim_end_matches(['\r', '\n']).to_owned();
+			if trim(content) != trim(equals) {
+				check_bail!(
+					"Exact mismatch",
+					format!("Exact mismatch: expected '{}', got '{}'", equals, content)
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+	openssl::sha::sha256(data)
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect()
+}
+
+pub fn verify_sha256(path: &str, contents: &[u8], expected: &str) -> CheckResult {
+	let actual = sha256_hex(contents);
+	if !actual.eq_ignore_ascii_case(expected) {
+		check_bail!(
+			"Content mismatch",
+			format!(
+				"Hash mismatch for '{}': expected '{}', got '{}'",
+				path, expected, actual
+			)
+		);
+	}
+	Ok(())
+}
+
+// written by upload checks and expected back verbatim
+pub fn random_contents() -> Vec<u8> {
+	rand::thread_rng()
+		.sample_iter(&Alphanumeric)
+		.take(64)
+		.collect()
+}
+
+pub fn verify_round_trip(path: &str, written: &[u8], read_back: &[u8]) -> CheckResult {
+	if read_back != written {
+		check_bail!(
+			"Content mismatch",
+			format!(
+				"Content mismatch for '{}': wrote '{}', read back '{}'",
+				path,
+				String::from_utf8_lossy(written),
+				String::from_utf8_lossy(read_back)
+			)
+		);
+	}
+	Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::{
+	checks::{check_bail, Check, CheckResult, Target},
+	config::check_types::{Dns, DnsProtocol, DnsRecord, DnsRecordType},
+};
+use async_trait::async_trait;
+use hickory_resolver::{
+	config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts},
+	error::ResolveErrorKind,
+	proto::rr::{Name, RData, RecordType},
+	TokioAsyncResolver,
+};
+use std::{
+	net::{IpAddr, SocketAddr},
+	str::FromStr,
+};
+
+impl From<DnsRecordType> for RecordType {
+	fn from(value: DnsRecordType) -> Self {
+		match value {
+			DnsRecordType::A => Self::A,
+			DnsRecordType::Aaaa => Self::AAAA,
+			DnsRecordType::Mx => Self::MX,
+			DnsRecordType::Txt => Self::TXT,
+			DnsRecordType::Cname => Self::CNAME,
+			DnsRecordType::Ptr => Self::PTR,
+		}
+	}
+}
+
+impl From<DnsProtocol> for Protocol {
+	fn from(value: DnsProtocol) -> Self {
+		match value {
+			DnsProtocol::Udp => Self::Udp,
+			DnsProtocol::Tcp => Self::Tcp,
+		}
+	}
+}
+
+// names are compared case-insensitively and without the root label, while
+// other answers such as TXT data must match exactly
+fn normalize(record_type: DnsRecordType, answer: &str) -> String {
+	match record_type {
+		DnsRecordType::Mx | DnsRecordType::Cname | DnsRecordType::Ptr => {
+			answer.trim_end_matches('.').to_lowercase()
+		}
+		DnsRecordType::A | DnsRecordType::Aaaa | DnsRecordType::Txt => answer.to_owned(),
+	}
+}
+
+fn format_answer(rdata: &RData) -> String {
+	match rdata {
+		RData::MX(mx) => mx.exchange().to_string(),
+		RData::TXT(txt) => txt
+			.txt_data()
+			.iter()
+			.map(|data| String::from_utf8_lossy(data))
+			.collect(),
+		rdata => rdata.to_string(),
+	}
+}
+
+async fn query(
+	resolver: &TokioAsyncResolver,
+	protocol: DnsProtocol,
+	record: &DnsRecord,
+) -> CheckResult {
+	let name = match IpAddr::from_str(&record.name) {
+		Ok(ip) => Name::from(ip),
+		// fully qualified so no search domains are appended
+		Err(_) => Name::from_str(&format!("{}.", record.name.trim_end_matches('.')))?,
+	};
+	let record_type = RecordType::from(record.record_type);
+
+	let lookup = match resolver.lookup(name, record_type).await {
+		Ok(lookup) => lookup,
+		Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => check_bail!(
+			"No records",
+			format!(
+				"No {} records for '{}' over {:?}",
+				record_type, record.name, protocol
+			)
+		),
+		Err(e) => check_bail!(
+			"Query failed",
+			format!(
+				"{} query for '{}' over {:?} failed: {}",
+				record_type, record.name, protocol, e
+			)
+		),
+	};
+
+	let answers: Vec<String> = lookup
+		.record_iter()
+		.filter(|rr| rr.record_type() == record_type)
+		.filter_map(|rr| rr.data())
+		.map(|rdata| normalize(record.record_type, &format_answer(rdata)))
+		.collect();
+
+	if answers.is_empty() {
+		check_bail!(
+			"No records",
+			format!(
+				"No {} records for '{}' over {:?}",
+				record_type, record.name, protocol
+			)
+		);
+	}
+
+	if let Some(missing) = record
+		.expected
+		.iter()
+		.find(|expected| !answers.contains(&normalize(record.record_type, expected)))
+	{
+		check_bail!(
+			"Record mismatch",
+			format!(
+				"{} records for '{}' over {:?} are missing '{}', got {:?}",
+				record_type, record.name, protocol, missing, answers
+			)
+		);
+	}
+
+	Ok(())
+}
+
+#[async_trait]
+impl Check for Dns {
+	async fn score(&self, &Target { ip, .. }: &Target<'_>) -> CheckResult {
+		for protocol in &self.protocols {
+			let name_server =
+				NameServerConfig::new(SocketAddr::new(ip, self.port), (*protocol).into());
+			let config = ResolverConfig::from_parts(None, vec![], vec![name_server]);
+
+			// every answer should come straight from the team's server
+			let mut opts = ResolverOpts::default();
+			opts.attempts = 1;
+			opts.cache_size = 0;
+			opts.use_hosts_file = false;
+			let resolver = TokioAsyncResolver::tokio(config, opts);
+
+			for record in &self.records {
+				query(&resolver, *protocol, record).await?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_names_are_normalized() {
+		assert_eq!(
+			normalize(DnsRecordType::Mx, "Mail.Example.COM."),
+			"mail.example.com"
+		);
+		assert_eq!(
+			normalize(DnsRecordType::Cname, "WWW.example.com."),
+			"www.example.com"
+		);
+		assert_eq!(
+			normalize(DnsRecordType::Ptr, "Host.example.com."),
+			"host.example.com"
+		);
+		assert_eq!(
+			normalize(DnsRecordType::Txt, "v=spf1 -all."),
+			"v=spf1 -all."
+		);
+		assert_eq!(normalize(DnsRecordType::Txt, "Token=AbC"), "Token=AbC");
+	}
+}
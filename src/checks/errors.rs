@@ -46,7 +46,7 @@ macro_rules! check_bail {
 }
 pub(crate) use check_bail;
 
-pub type CheckResult = Result<(), CheckError>;
+pub type CheckResult<T = ()> = Result<T, CheckError>;
 
 pub fn get_check_result_errors(res: &CheckResult) -> (String, String) {
 	match res {
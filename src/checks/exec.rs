@@ -0,0 +1,101 @@
+use crate::{
+	checks::{check_bail, errors::check_error, Check, CheckResult, Target},
+	config::check_types::Exec,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{net::IpAddr, process::Stdio};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+// written to the script's stdin
+#[derive(Serialize)]
+struct ExecInput<'a> {
+	ip: IpAddr,
+	host: &'a str,
+	team: &'a str,
+	vm: &'a str,
+	service: &'a str,
+	params: &'a serde_json::Map<String, serde_json::Value>,
+}
+
+// read from the script's stdout
+#[derive(Deserialize)]
+struct ExecOutput {
+	up: bool,
+	short_error: Option<String>,
+	long_error: Option<String>,
+	points: Option<i32>,
+}
+
+impl Exec {
+	async fn run(&self, target: &Target<'_>) -> CheckResult<ExecOutput> {
+		let input = serde_json::to_vec(&ExecInput {
+			ip: target.ip,
+			host: target.host,
+			team: target.team,
+			vm: target.vm_alias,
+			service: target.service,
+			params: &self.params,
+		})?;
+
+		// killed if the check times out
+		let mut child = Command::new(&self.command)
+			.args(&self.args)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.kill_on_drop(true)
+			.spawn()
+			.map_err(|e| {
+				check_error!(
+					"Internal server error",
+					format!("Failed to run '{}': {}", self.command.display(), e)
+				)
+			})?;
+
+		// the script may exit without reading its input
+		if let Some(mut stdin) = child.stdin.take() {
+			let _ = stdin.write_all(&input).await;
+		}
+
+		let output = child.wait_with_output().await?;
+		match serde_json::from_slice(&output.stdout) {
+			Ok(result) => Ok(result),
+			Err(e) => check_bail!(
+				"Script failed",
+				format!(
+					"'{}' ({}) produced no valid result ({}): {}",
+					self.command.display(),
+					output.status,
+					e,
+					String::from_utf8_lossy(&output.stderr).trim()
+				)
+			),
+		}
+	}
+}
+
+#[async_trait]
+impl Check for Exec {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		self.score_with_points(target).await.0
+	}
+
+	async fn score_with_points(&self, target: &Target<'_>) -> (CheckResult, Option<i32>) {
+		let output = match self.run(target).await {
+			Ok(output) => output,
+			Err(e) => return (Err(e), None),
+		};
+
+		let res = if output.up {
+			Ok(())
+		} else {
+			let short = output
+				.short_error
+				.unwrap_or_else(|| "Service down".to_owned());
+			let long = output.long_error.unwrap_or_else(|| short.clone());
+			Err(check_error!(short, long))
+		};
+		(res, output.points)
+	}
+}
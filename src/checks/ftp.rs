@@ -0,0 +1,340 @@
+use crate::{
+	checks::{
+		check_bail,
+		conn::{self, BoxedStream, Conn},
+		content,
+		errors::check_error,
+		Check, CheckResult, Target,
+	},
+	config::check_types::{FileCheck, Ftp, FtpAuthType, FtpMode},
+};
+use async_trait::async_trait;
+use log::debug;
+use std::net::IpAddr;
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	net::{TcpListener, TcpStream},
+};
+
+// largest file downloaded before the transfer is failed
+const MAX_FILE_LEN: u64 = 64 * 1024 * 1024;
+
+// data connection as set up before the transfer command is sent
+enum DataConn {
+	Passive(TcpStream),
+	Active(TcpListener),
+}
+
+// the data port from a PASV reply's (h1,h2,h3,h4,p1,p2); the advertised host
+// is ignored since it is frequently wrong behind NAT
+fn parse_pasv(text: &str) -> Option<u16> {
+	let (_, fields) = text.split_once('(')?;
+	let (fields, _) = fields.split_once(')')?;
+	let numbers = fields
+		.split(',')
+		.map(|n| n.trim().parse::<u8>().ok())
+		.collect::<Option<Vec<u8>>>()?;
+	let [_, _, _, _, p1, p2] = numbers[..] else {
+		return None;
+	};
+	Some(u16::from(p1) << 8 | u16::from(p2))
+}
+
+// the data port from an EPSV reply's (|||port|), since PASV can't describe
+// IPv6 addresses; the delimiter is usually `|` but may be any character
+// (RFC 2428)
+fn parse_epsv(text: &str) -> Option<u16> {
+	let (_, fields) = text.split_once('(')?;
+	let (fields, _) = fields.split_once(')')?;
+	let delimiter = fields.chars().next()?;
+	match fields.split(delimiter).collect::<Vec<_>>()[..] {
+		["", "", "", port, ""] => port.parse().ok(),
+		_ => None,
+	}
+}
+
+struct Session<'a> {
+	cfg: &'a Ftp,
+	ip: IpAddr,
+	conn: Conn,
+}
+
+impl<'a> Session<'a> {
+	async fn open(cfg: &'a Ftp, target: &Target<'_>) -> CheckResult<Session<'a>> {
+		let ip = target.ip;
+		let mut conn = Conn::open(ip, cfg.port, false).await?;
+		conn.expect_reply("connect", &[220], "Connection failed")
+			.await?;
+
+		if cfg.tls {
+			conn.command("AUTH TLS", &[234], "TLS negotiation failed")
+				.await?;
+			conn = conn.upgrade().await?;
+		}
+
+		let (user, password) = match &cfg.auth {
+			FtpAuthType::Anonymous => ("anonymous", "cobalt@"),
+			FtpAuthType::Password { user, password } => {
+				(user.as_str(), target.password(user, password))
+			}
+		};
+		conn.send_line(&format!("USER {}", user)).await?;
+		let (code, text) = conn.read_reply().await?;
+		match code {
+			230 => (),
+			331 => {
+				conn.command(
+					&format!("PASS {}", password),
+					&[230],
+					"Authentication failed",
+				)
+				.await?;
+			}
+			_ => check_bail!(
+				"Authentication failed",
+				format!("'USER' failed: got '{}'", text)
+			),
+		}
+
+		if cfg.tls {
+			conn.command("PBSZ 0", &[200], "TLS negotiation failed")
+				.await?;
+			conn.command("PROT P", &[200], "TLS negotiation failed")
+				.await?;
+		}
+		conn.command("TYPE I", &[200], "Transfer failed").await?;
+
+		Ok(Session { cfg, ip, conn })
+	}
+
+	async fn prepare_data(&mut self) -> CheckResult<DataConn> {
+		match self.cfg.mode {
+			FtpMode::Passive => {
+				let port = match self.ip {
+					IpAddr::V4(_) => self.pasv().await?,
+					IpAddr::V6(_) => self.epsv().await?,
+				};
+				Ok(DataConn::Passive(conn::connect(self.ip, port).await?))
+			}
+			FtpMode::Active => {
+				let local_ip = self.conn.local_ip;
+				let listener = TcpListener::bind((local_ip, 0)).await?;
+				let port = listener.local_addr()?.port();
+				let command = match local_ip {
+					IpAddr::V4(local_ip) => {
+						let [a, b, c, d] = local_ip.octets();
+						format!("PORT {},{},{},{},{},{}", a, b, c, d, port / 256, port % 256)
+					}
+					// RFC 2428
+					IpAddr::V6(local_ip) => format!("EPRT |2|{}|{}|", local_ip, port),
+				};
+				self.conn
+					.command(&command, &[200], "Transfer failed")
+					.await?;
+
+				Ok(DataConn::Active(listener))
+			}
+		}
+	}
+
+	async fn pasv(&mut self) -> CheckResult<u16> {
+		let text = self.conn.command("PASV", &[227], "Transfer failed").await?;
+		let Some(port) = parse_pasv(&text) else {
+			check_bail!(
+				"Transfer failed",
+				format!("Malformed PASV reply: '{}'", text)
+			);
+		};
+		Ok(port)
+	}
+
+	async fn epsv(&mut self) -> CheckResult<u16> {
+		let text = self.conn.command("EPSV", &[229], "Transfer failed").await?;
+		let Some(port) = parse_epsv(&text) else {
+			check_bail!(
+				"Transfer failed",
+				format!("Malformed EPSV reply: '{}'", text)
+			);
+		};
+		Ok(port)
+	}
+
+	async fn open_data(&mut self, data: DataConn) -> CheckResult<BoxedStream> {
+		let stream = match data {
+			DataConn::Passive(stream) => stream,
+			DataConn::Active(listener) => listener.accept().await?.0,
+		};
+
+		// data channels resume the control channel's TLS session
+		match &self.conn.tls {
+			Some(tls) => tls.connect(self.ip, stream).await,
+			None => Ok(Box::new(stream)),
+		}
+	}
+
+	async fn retrieve(&mut self, path: &str) -> CheckResult<Vec<u8>> {
+		let data = self.prepare_data().await?;
+		let command = format!("RETR {}", path);
+		self.conn
+			.command(&command, &[125, 150], "Transfer failed")
+			.await?;
+
+		let mut stream = self.open_data(data).await?;
+		let mut contents = Vec::new();
+		// one byte past the limit tells an oversized file apart from one that
+		// fits exactly
+		(&mut stream)
+			.take(MAX_FILE_LEN + 1)
+			.read_to_end(&mut contents)
+			.await
+			.map_err(|e| {
+				check_error!(
+					"Transfer failed",
+					format!("Failed to read '{}': {}", path, e)
+				)
+			})?;
+		// OpenSSL won't resume a session whose connection wasn't shut down, so
+		// this keeps later transfers able to reuse it; the server may well have
+		// closed its end already
+		let _ = stream.shutdown().await;
+		drop(stream);
+		if contents.len() as u64 > MAX_FILE_LEN {
+			check_bail!(
+				"Transfer failed",
+				format!("'{}' is larger than {} bytes", path, MAX_FILE_LEN)
+			);
+		}
+
+		self.conn
+			.expect_reply(&command, &[226, 250], "Transfer failed")
+			.await?;
+		Ok(contents)
+	}
+
+	async fn store(&mut self, path: &str, contents: &[u8]) -> CheckResult {
+		let data = self.prepare_data().await?;
+		let command = format!("STOR {}", path);
+		self.conn
+			.command(&command, &[125, 150], "Transfer failed")
+			.await?;
+
+		let mut stream = self.open_data(data).await?;
+		let written = async {
+			stream.write_all(contents).await?;
+			stream.shutdown().await
+		};
+		written.await.map_err(|e| {
+			check_error!(
+				"Transfer failed",
+				format!("Failed to write '{}': {}", path, e)
+			)
+		})?;
+		// reading until the server closes its end picks up the TLS session
+		// ticket it issued, for the next transfer to resume
+		let _ = tokio::io::copy(&mut stream, &mut tokio::io::sink()).await;
+		drop(stream);
+
+		self.conn
+			.expect_reply(&command, &[226, 250], "Transfer failed")
+			.await?;
+		Ok(())
+	}
+
+	async fn check_file(&mut self, file: &FileCheck) -> CheckResult {
+		match file {
+			FileCheck::Download { path, sha256 } => {
+				let contents = self.retrieve(path).await?;
+				if let Some(expected) = sha256 {
+					content::verify_sha256(path, &contents, expected)?;
+				}
+			}
+			FileCheck::Upload { path } => {
+				let contents = content::random_contents();
+				self.store(path, &contents).await?;
+				let read_back = self.retrieve(path).await?;
+
+				// cleanup is best-effort and doesn't affect the result
+				if let Err(e) = self
+					.conn
+					.command(&format!("DELE {}", path), &[250], "Transfer failed")
+					.await
+				{
+					debug!("Failed to delete '{}' after upload: {}", path, e.long);
+				}
+
+				content::verify_round_trip(path, &contents, &read_back)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[async_trait]
+impl Check for Ftp {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let mut session = Session::open(self, target).await?;
+		for file in &self.files {
+			session.check_file(file).await?;
+		}
+
+		let _ = session.conn.send_line("QUIT").await;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn pasv_replies() {
+		assert_eq!(
+			parse_pasv("227 Entering Passive Mode (10,1,2,3,195,80)."),
+			Some(50000)
+		);
+		assert_eq!(
+			parse_pasv("227 Entering Passive Mode (10,1,2,3,0,21)"),
+			Some(21)
+		);
+		// fields are bytes, so the port can't overflow
+		assert_eq!(
+			parse_pasv("227 Entering Passive Mode (10,1,2,3,256,0)"),
+			None
+		);
+		assert_eq!(
+			parse_pasv("227 Entering Passive Mode (10,1,2,3,300,300)"),
+			None
+		);
+		assert_eq!(
+			parse_pasv("227 Entering Passive Mode 10,1,2,3,195,80"),
+			None
+		);
+		assert_eq!(parse_pasv("227 Entering Passive Mode (195,80)"), None);
+		assert_eq!(
+			parse_pasv("227 Entering Passive Mode (10,1,2,3,4,195,80)"),
+			None
+		);
+	}
+
+	#[test]
+	fn epsv_replies() {
+		assert_eq!(
+			parse_epsv("229 Entering Extended Passive Mode (|||50000|)"),
+			Some(50000)
+		);
+		assert_eq!(
+			parse_epsv("229 Entering Extended Passive Mode |||50000|"),
+			None
+		);
+		assert_eq!(
+			parse_epsv("229 Entering Extended Passive Mode (|||70000|)"),
+			None
+		);
+		assert_eq!(parse_epsv("229 Entering Extended Passive Mode (|||)"), None);
+		assert_eq!(
+			parse_epsv("229 Entering Extended Passive Mode (||50000|)"),
+			None
+		);
+	}
+}
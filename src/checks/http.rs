@@ -1,40 +1,314 @@
 use crate::{
-	checks::{check_bail, Check, CheckResult},
-	config::{check_types::Http, Vm},
+	checks::{
+		check_bail,
+		errors::{check_error, CheckError},
+		Check, CheckResult, Target,
+	},
+	config::check_types::{Capture, ContentMatch, Http, HttpScheme, TlsOptions},
 };
 use async_trait::async_trait;
-use std::net::Ipv4Addr;
+use itertools::Itertools;
+use openssl::{asn1::Asn1Time, nid::Nid, x509::X509};
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use std::{
+	collections::HashMap,
+	error::Error,
+	net::{IpAddr, Ipv6Addr, SocketAddr},
+};
 use url::Url;
 
+// values captured from earlier pages of the same check run, substituted into
+// later pages wherever `{{name}}` appears
+struct Variables {
+	values: HashMap<String, String>,
+	placeholder: Regex,
+}
+
+impl Variables {
+	fn new() -> Self {
+		Self {
+			values: HashMap::new(),
+			placeholder: Regex::new(r"\{\{\s*(\w+)\s*\}\}").unwrap(),
+		}
+	}
+
+	fn substitute(&self, template: &str) -> CheckResult<String> {
+		if let Some(name) = self
+			.placeholder
+			.captures_iter(template)
+			.map(|caps| caps[1].to_owned())
+			.find(|name| !self.values.contains_key(name))
+		{
+			check_bail!(
+				"Unknown variable",
+				format!("'{}' refers to unknown variable '{}'", template, name)
+			);
+		}
+
+		Ok(self
+			.placeholder
+			.replace_all(template, |caps: &regex::Captures| {
+				self.values[&caps[1]].to_owned()
+			})
+			.into_owned())
+	}
+
+	fn capture(
+		&mut self,
+		name: &str,
+		capture: &Capture,
+		headers: &HeaderMap,
+		body: &str,
+	) -> CheckResult {
+		let value = match capture {
+			Capture::Regex { regex, group } => regex
+				.captures(body)
+				.and_then(|caps| caps.get(*group))
+				.map(|value| value.as_str().to_owned()),
+			Capture::JsonPointer { pointer } => serde_json::from_str::<serde_json::Value>(body)
+				.ok()
+				.and_then(|json| {
+					json.pointer(pointer).map(|value| match value {
+						serde_json::Value::String(value) => value.to_owned(),
+						value => value.to_string(),
+					})
+				}),
+			Capture::Header { name } => headers
+				.get(name)
+				.and_then(|value| value.to_str().ok())
+				.map(str::to_owned),
+		};
+
+		let Some(value) = value else {
+			check_bail!(
+				"Capture failed",
+				format!(
+					"Failed to capture '{}' ({:?}) from '{}'",
+					name, capture, body
+				)
+			);
+		};
+		self.values.insert(name.to_owned(), value);
+
+		Ok(())
+	}
+}
+
+// one client serves every page of a run, so connections are reused and
+// cookies carry over
+fn build_client(http: &Http, host: &str, ip: IpAddr) -> CheckResult<reqwest::Client> {
+	let mut builder = reqwest::Client::builder().cookie_store(true).tls_info(true);
+
+	// connect to the team's box regardless of what the hostnames resolve to;
+	// the port is taken from each page's URL
+	for host in http
+		.pages
+		.iter()
+		.map(|page| page.host.as_deref().unwrap_or(host))
+		.unique()
+	{
+		builder = builder.resolve(host, SocketAddr::new(ip, 0));
+	}
+
+	// validated to be the same for every HTTPS page
+	if let Some(page) = http
+		.pages
+		.iter()
+		.find(|page| matches!(page.scheme, HttpScheme::Https))
+	{
+		builder = builder.danger_accept_invalid_certs(!page.tls.verify);
+
+		if let Some(ca_bundle) = &page.tls.ca_bundle {
+			builder = builder.tls_built_in_root_certs(false);
+			for cert in &ca_bundle.certs {
+				builder = builder.add_root_certificate(cert.clone());
+			}
+		}
+	}
+
+	Ok(builder.build()?)
+}
+
+// whether a certificate name, which may have a wildcard as its leftmost label
+// (`*.team1.local`), covers `name`; the wildcard spans exactly one label and
+// not a bare top-level domain (RFC 6125)
+fn name_matches(pattern: &str, name: &str) -> bool {
+	match pattern.strip_prefix("*.") {
+		Some(suffix) if suffix.contains('.') => name
+			.split_once('.')
+			.is_some_and(|(label, rest)| !label.is_empty() && rest.eq_ignore_ascii_case(suffix)),
+		_ => pattern.eq_ignore_ascii_case(name),
+	}
+}
+
+fn check_certificate(tls: &TlsOptions, der: Option<&[u8]>) -> CheckResult {
+	let Some(der) = der else {
+		check_bail!(
+			"Missing certificate",
+			"Server did not present a certificate"
+		);
+	};
+	let cert = X509::from_der(der)?;
+
+	if let Some(expected_name) = &tls.expected_name {
+		let common_names = cert
+			.subject_name()
+			.entries_by_nid(Nid::COMMONNAME)
+			.filter_map(|entry| entry.data().as_utf8().ok())
+			.map(|name| name.to_string());
+		let alt_names = cert
+			.subject_alt_names()
+			.into_iter()
+			.flatten()
+			.filter_map(|name| name.dnsname().map(str::to_owned));
+		let names: Vec<String> = common_names.chain(alt_names).collect();
+
+		if !names.iter().any(|name| name_matches(name, expected_name)) {
+			check_bail!(
+				"Certificate name mismatch",
+				format!(
+					"Certificate name mismatch: expected '{}', got {:?}",
+					expected_name, names
+				)
+			);
+		}
+	}
+
+	if let Some(min_days) = tls.min_days_to_expiry {
+		if cert.not_after() < Asn1Time::days_from_now(min_days)? {
+			check_bail!(
+				"Certificate expiring",
+				format!(
+					"Certificate expires at {}, less than {} days from now",
+					cert.not_after(),
+					min_days
+				)
+			);
+		}
+	}
+
+	Ok(())
+}
+
+fn send_error(url: &Url, err: reqwest::Error) -> CheckError {
+	let tls_failed = std::iter::successors(err.source(), |err| (*err).source())
+		.any(|err| err.is::<native_tls::Error>());
+
+	if tls_failed {
+		check_error!(
+			"TLS handshake failed",
+			format!("TLS handshake with '{}' failed: {}", url, err)
+		)
+	} else if err.is_connect() {
+		check_error!(
+			"Connection failed",
+			format!("Failed to connect to '{}': {}", url, err)
+		)
+	} else {
+		err.into()
+	}
+}
+
 #[async_trait]
 impl Check for Http {
-	async fn score(&self, ip: Ipv4Addr, _: &Vm) -> CheckResult {
+	async fn score(&self, &Target { ip, host, .. }: &Target<'_>) -> CheckResult {
+		// cookies and captures carry over between the pages of a single run
+		let client = build_client(self, host, ip)?;
+		let mut vars = Variables::new();
+
 		for page in &self.pages {
-			let url_raw = format!("http://{}", ip);
-			let client = reqwest::Client::new();
-			let mut req = client.request(page.method.to_owned(), Url::parse(&url_raw)?);
+			let port = page.port.unwrap_or_else(|| page.scheme.default_port());
+			let host = page.host.as_deref().unwrap_or(host);
+			// IPv6 literals are bracketed in URLs
+			let authority = match host.parse::<Ipv6Addr>() {
+				Ok(_) => format!("[{}]:{}", host, port),
+				Err(_) => format!("{}:{}", host, port),
+			};
+			let url = Url::parse(&format!("{}://{}", page.scheme, authority))?
+				.join(&vars.substitute(&page.path)?)?;
+			let mut req = client.request(page.method.to_owned(), url.clone());
 
 			if let Some(headers) = &page.headers {
 				for (key, value) in headers {
-					req = req.header(key, value);
+					req = req.header(key, vars.substitute(value)?);
 				}
 			}
 
 			if let Some(body) = &page.body {
-				req = req.body(body.to_owned());
+				req = req.body(vars.substitute(body)?);
+			}
+
+			let res = req.send().await.map_err(|err| send_error(&url, err))?;
+			if let HttpScheme::Https = page.scheme {
+				let tls_info = res.extensions().get::<reqwest::tls::TlsInfo>();
+				check_certificate(&page.tls, tls_info.and_then(|info| info.peer_certificate()))?;
 			}
 
-			let res = req.send().await?.text().await?;
-			if let Some(contains) = &page.contains {
-				if !res.contains(contains) {
+			let status = res.status();
+			if let Some(expected_status) = &page.expected_status {
+				if !expected_status.matches(status.as_u16()) {
 					check_bail!(
-						"Content mismatch",
-						format!("Content mismatch: expected '{}', got '{}'", contains, res)
+						"Unexpected status",
+						format!(
+							"Unexpected status from '{}': expected {}, got {}",
+							url, expected_status, status
+						)
 					);
 				}
 			}
+
+			let headers = res.headers().clone();
+			let res = res.text().await?;
+			let content = ContentMatch {
+				contains: page
+					.content
+					.contains
+					.as_deref()
+					.map(|contains| vars.substitute(contains))
+					.transpose()?,
+				contains_regex: page.content.contains_regex.clone(),
+				equals: page
+					.content
+					.equals
+					.as_deref()
+					.map(|equals| vars.substitute(equals))
+					.transpose()?,
+			};
+			content.verify(&res)?;
+
+			if let Some(captures) = &page.captures {
+				for (name, capture) in captures {
+					vars.capture(name, capture, &headers, &res)?;
+				}
+			}
 		}
 
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn exact_names() {
+		assert!(name_matches("www.team1.local", "www.team1.local"));
+		assert!(name_matches("WWW.team1.local", "www.TEAM1.local"));
+		assert!(!name_matches("www.team1.local", "www.team2.local"));
+	}
+
+	#[test]
+	fn wildcard_names() {
+		assert!(name_matches("*.team1.local", "www.team1.local"));
+		assert!(name_matches("*.team1.local", "MAIL.team1.local"));
+		// only the leftmost label
+		assert!(!name_matches("*.team1.local", "a.www.team1.local"));
+		assert!(!name_matches("*.team1.local", "team1.local"));
+		assert!(!name_matches("*.team1.local", ".team1.local"));
+		assert!(!name_matches("www.*.local", "www.team1.local"));
+		// never a whole top-level domain
+		assert!(!name_matches("*.local", "team1.local"));
+	}
+}
@@ -0,0 +1,147 @@
+use crate::{
+	checks::{check_bail, Check, CheckResult, Target},
+	config::check_types::Icmp,
+};
+use async_trait::async_trait;
+use rand::Rng;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{net::IpAddr, time::Duration};
+use tokio::{net::UdpSocket, time::Instant};
+
+// how long to wait for each echo reply
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+const ECHO_REQUEST_V4: u8 = 8;
+const ECHO_REPLY_V4: u8 = 0;
+const ECHO_REQUEST_V6: u8 = 128;
+const ECHO_REPLY_V6: u8 = 129;
+
+fn checksum(data: &[u8]) -> u16 {
+	let mut sum: u32 = data
+		.chunks(2)
+		.map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]) as u32)
+		.sum();
+	while sum >> 16 != 0 {
+		sum = (sum & 0xffff) + (sum >> 16);
+	}
+	!(sum as u16)
+}
+
+fn echo_request(ip: IpAddr, ident: u16, seq: u16, payload: &[u8]) -> Vec<u8> {
+	let kind = if ip.is_ipv4() {
+		ECHO_REQUEST_V4
+	} else {
+		ECHO_REQUEST_V6
+	};
+	let mut packet = vec![kind, 0, 0, 0];
+	packet.extend_from_slice(&ident.to_be_bytes());
+	packet.extend_from_slice(&seq.to_be_bytes());
+	packet.extend_from_slice(payload);
+	// the kernel fills in ICMPv6 checksums, which cover a pseudo-header
+	if ip.is_ipv4() {
+		let sum = checksum(&packet);
+		packet[2..4].copy_from_slice(&sum.to_be_bytes());
+	}
+	packet
+}
+
+// unprivileged ping sockets are preferred, falling back to a raw socket when
+// they are disabled; raw IPv4 sockets also deliver the IP header, which is
+// flagged so it can be skipped
+fn icmp_socket(ip: IpAddr) -> std::io::Result<(UdpSocket, bool)> {
+	let (domain, protocol) = match ip {
+		IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+		IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+	};
+	let (socket, has_header) = match Socket::new(domain, Type::DGRAM, Some(protocol)) {
+		Ok(socket) => (socket, false),
+		Err(_) => (
+			Socket::new(domain, Type::RAW, Some(protocol))?,
+			ip.is_ipv4(),
+		),
+	};
+	socket.set_nonblocking(true)?;
+	Ok((
+		UdpSocket::from_std(std::net::UdpSocket::from(socket))?,
+		has_header,
+	))
+}
+
+pub fn preflight(ip: IpAddr) -> anyhow::Result<()> {
+	icmp_socket(ip).map(drop).map_err(|e| {
+		anyhow::anyhow!(
+			"failed to open an {} ICMP socket ({}); add the user's group to net.ipv4.ping_group_range or grant CAP_NET_RAW",
+			if ip.is_ipv4() { "IPv4" } else { "IPv6" },
+			e
+		)
+	})
+}
+
+// waits for the reply to `seq`, identified by its payload since ping sockets
+// rewrite the identifier
+async fn await_reply(
+	socket: &UdpSocket,
+	ip: IpAddr,
+	has_header: bool,
+	seq: u16,
+	payload: &[u8],
+) -> bool {
+	let reply = if ip.is_ipv4() {
+		ECHO_REPLY_V4
+	} else {
+		ECHO_REPLY_V6
+	};
+	let deadline = Instant::now() + REPLY_TIMEOUT;
+	let mut buf = [0; 1500];
+	while let Ok(Ok((len, _))) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await
+	{
+		let packet = &buf[..len];
+		let packet = if has_header {
+			let header_len = usize::from(packet.first().map_or(0, |b| b & 0x0f)) * 4;
+			packet.get(header_len..).unwrap_or_default()
+		} else {
+			packet
+		};
+
+		if packet.len() >= 8
+			&& packet[0] == reply
+			&& packet[6..8] == seq.to_be_bytes()
+			&& &packet[8..] == payload
+		{
+			return true;
+		}
+	}
+	false
+}
+
+#[async_trait]
+impl Check for Icmp {
+	async fn score(&self, &Target { ip, .. }: &Target<'_>) -> CheckResult {
+		let (socket, has_header) = icmp_socket(ip)?;
+		socket.connect((ip, 0)).await?;
+
+		let ident: u16 = rand::thread_rng().gen();
+		let mut received = 0;
+		for seq in 0..self.count {
+			let payload: [u8; 16] = rand::thread_rng().gen();
+			socket.send(&echo_request(ip, ident, seq, &payload)).await?;
+			if await_reply(&socket, ip, has_header, seq, &payload).await {
+				received += 1;
+			}
+		}
+
+		let lost = self.count - received;
+		let loss = u32::from(lost) * 100 / u32::from(self.count.max(1));
+		if loss > u32::from(self.max_loss) {
+			check_bail!(
+				"Packet loss",
+				format!(
+					"Lost {}/{} packets to {} ({}%), at most {}% allowed",
+					lost, self.count, ip, loss, self.max_loss
+				)
+			);
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,165 @@
+use crate::{
+	checks::{
+		check_bail,
+		conn::{self, Conn},
+		smtp, Check, CheckResult, Target,
+	},
+	config::check_types::{Imap, MailTls},
+};
+use async_trait::async_trait;
+
+fn quote(value: &str) -> String {
+	format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+struct Session {
+	conn: Conn,
+	tag: u32,
+}
+
+impl Session {
+	async fn open(cfg: &Imap, target: &Target<'_>) -> CheckResult<Self> {
+		let password = target.password(&cfg.auth.user, &cfg.auth.password);
+		let port = cfg.port.unwrap_or(match cfg.tls {
+			MailTls::Implicit => 993,
+			MailTls::None | MailTls::Starttls => 143,
+		});
+		let mut session = Self {
+			conn: Conn::open(target.ip, port, cfg.tls == MailTls::Implicit).await?,
+			tag: 0,
+		};
+
+		let greeting = session.conn.read_line().await?;
+		if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+			check_bail!(
+				"Connection failed",
+				format!("Unexpected greeting: '{}'", greeting)
+			);
+		}
+
+		if cfg.tls == MailTls::Starttls {
+			session
+				.command("STARTTLS", "TLS negotiation failed")
+				.await?;
+			session.conn = session.conn.upgrade().await?;
+		}
+
+		session
+			.command(
+				&format!("LOGIN {} {}", quote(&cfg.auth.user), quote(password)),
+				"Authentication failed",
+			)
+			.await?;
+
+		Ok(session)
+	}
+
+	// sends a tagged command, returning its untagged responses with any
+	// literals inlined
+	async fn command(&mut self, command: &str, short: &'static str) -> CheckResult<Vec<String>> {
+		self.tag += 1;
+		let tag = format!("a{}", self.tag);
+		self.conn.send_line(&format!("{} {}", tag, command)).await?;
+
+		let mut responses = Vec::new();
+		loop {
+			let mut line = self.conn.read_line().await?;
+			// a line ending in `{n}` is followed by n raw bytes and the rest of
+			// the response
+			while let Some(len) = line
+				.strip_suffix('}')
+				.and_then(|line| line.rsplit_once('{'))
+				.and_then(|(_, len)| len.parse().ok())
+			{
+				let literal = self.conn.read_exact(len).await?;
+				line.push_str(&String::from_utf8_lossy(&literal));
+				line.push_str(&self.conn.read_line().await?);
+			}
+
+			if let Some(status) = line.strip_prefix(&tag) {
+				if !status.starts_with(" OK") {
+					check_bail!(
+						short,
+						format!("'{}' failed: got '{}'", conn::command_name(command), line)
+					);
+				}
+				return Ok(responses);
+			}
+			responses.push(line);
+		}
+	}
+
+	// selects the mailbox, returning its message count
+	async fn select(&mut self, mailbox: &str) -> CheckResult<usize> {
+		let responses = self
+			.command(&format!("SELECT {}", quote(mailbox)), "Mailbox unavailable")
+			.await?;
+		Ok(responses
+			.iter()
+			.filter_map(|line| line.strip_prefix("* ")?.strip_suffix(" EXISTS"))
+			.find_map(|count| count.parse().ok())
+			.unwrap_or_default())
+	}
+
+	async fn search(&mut self, token: &str) -> CheckResult<Vec<usize>> {
+		let responses = self
+			.command(
+				&format!("SEARCH SUBJECT {}", quote(token)),
+				"Protocol error",
+			)
+			.await?;
+		Ok(responses
+			.iter()
+			.filter_map(|line| line.strip_prefix("* SEARCH"))
+			.flat_map(|ids| ids.split_whitespace())
+			.filter_map(|id| id.parse().ok())
+			.collect())
+	}
+
+	async fn logout(mut self) {
+		let _ = self.command("LOGOUT", "Protocol error").await;
+	}
+}
+
+#[async_trait]
+impl Check for Imap {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let Some(smtp_alias) = &self.confirm_delivery else {
+			let mut session = Session::open(self, target).await?;
+			let count = session.select(&self.mailbox).await?;
+			if self.fetch && count > 0 {
+				session
+					.command(
+						&format!("FETCH {} BODY.PEEK[HEADER]", count),
+						"Retrieval failed",
+					)
+					.await?;
+			}
+			session.logout().await;
+			return Ok(());
+		};
+
+		let mailbox = format!("'{}'", self.mailbox);
+		smtp::confirm_delivery(target, smtp_alias, &mailbox, |token| async move {
+			let mut session = Session::open(self, target).await?;
+			session.select(&self.mailbox).await?;
+			let ids = session.search(&token).await?;
+			if ids.is_empty() {
+				session.logout().await;
+				return Ok(false);
+			}
+
+			let ids: Vec<String> = ids.iter().map(usize::to_string).collect();
+			session
+				.command(
+					&format!("STORE {} +FLAGS.SILENT (\\Deleted)", ids.join(",")),
+					"Protocol error",
+				)
+				.await?;
+			session.command("EXPUNGE", "Protocol error").await?;
+			session.logout().await;
+			Ok(true)
+		})
+		.await
+	}
+}
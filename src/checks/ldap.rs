@@ -0,0 +1,156 @@
+use crate::{
+	checks::{check_bail, errors::check_error, errors::CheckError, Check, CheckResult, Target},
+	config::check_types::{Ldap, LdapScope, LdapSearch, LdapTls},
+};
+use async_trait::async_trait;
+use ldap3::{LdapConnAsync, LdapConnSettings, LdapError, Scope, SearchEntry};
+use log::debug;
+use std::net::SocketAddr;
+
+// LDAP result code for a rejected bind
+const INVALID_CREDENTIALS: u32 = 49;
+
+fn connect_error(url: &str, err: LdapError) -> CheckError {
+	match err {
+		LdapError::NativeTLS { .. } => check_error!(
+			"TLS negotiation failed",
+			format!("TLS negotiation with '{}' failed: {}", url, err)
+		),
+		_ => check_error!(
+			"Connection failed",
+			format!("Failed to connect to '{}': {}", url, err)
+		),
+	}
+}
+
+fn verify_entries(search: &LdapSearch, entries: &[SearchEntry]) -> CheckResult {
+	if search.entries.is_empty() && search.attributes.is_empty() && entries.is_empty() {
+		check_bail!(
+			"No entries",
+			format!(
+				"Search for '{}' under '{}' returned no entries",
+				search.filter, search.base
+			)
+		);
+	}
+
+	for dn in &search.entries {
+		if !entries
+			.iter()
+			.any(|entry| entry.dn.eq_ignore_ascii_case(dn))
+		{
+			check_bail!(
+				"Entry missing",
+				format!(
+					"Search for '{}' under '{}' did not return '{}'",
+					search.filter, search.base, dn
+				)
+			);
+		}
+	}
+
+	for (name, expected) in &search.attributes {
+		let found = entries.iter().any(|entry| {
+			entry
+				.attrs
+				.iter()
+				.find(|(attr, _)| attr.eq_ignore_ascii_case(name))
+				.is_some_and(|(_, values)| expected.iter().all(|value| values.contains(value)))
+		});
+		if !found {
+			check_bail!(
+				"Attribute mismatch",
+				format!(
+					"No entry returned by '{}' under '{}' has attribute '{}' with values {:?}",
+					search.filter, search.base, name, expected
+				)
+			);
+		}
+	}
+
+	Ok(())
+}
+
+#[async_trait]
+impl Check for Ldap {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let ip = target.ip;
+		let (scheme, default_port) = match self.tls {
+			LdapTls::Ldaps => ("ldaps", 636),
+			LdapTls::None | LdapTls::Starttls => ("ldap", 389),
+		};
+		let url = format!(
+			"{}://{}",
+			scheme,
+			SocketAddr::new(ip, self.port.unwrap_or(default_port))
+		);
+		let settings = LdapConnSettings::new()
+			.set_starttls(self.tls == LdapTls::Starttls)
+			.set_no_tls_verify(true);
+
+		let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &url)
+			.await
+			.map_err(|e| connect_error(&url, e))?;
+		// exits once `ldap` is dropped
+		tokio::spawn(async move {
+			if let Err(e) = conn.drive().await {
+				debug!("LDAP connection error: {}", e);
+			}
+		});
+
+		// an empty password makes a simple bind unauthenticated (RFC 4513), which
+		// most servers accept without checking any credentials
+		let password = target.password(&self.bind_dn, &self.password);
+		if password.is_empty() {
+			check_bail!(
+				"Authentication failed",
+				format!("No password to bind as '{}' with", self.bind_dn)
+			);
+		}
+		let bind = ldap.simple_bind(&self.bind_dn, password).await?;
+		if bind.rc == INVALID_CREDENTIALS {
+			check_bail!(
+				"Authentication failed",
+				format!("Bind as '{}' failed: {}", self.bind_dn, bind)
+			);
+		} else if bind.rc != 0 {
+			check_bail!(
+				"Bind failed",
+				format!("Bind as '{}' failed: {}", self.bind_dn, bind)
+			);
+		}
+
+		if let Some(search) = &self.search {
+			let scope = match search.scope {
+				LdapScope::Base => Scope::Base,
+				LdapScope::OneLevel => Scope::OneLevel,
+				LdapScope::Subtree => Scope::Subtree,
+			};
+			let attributes: Vec<&str> = if search.attributes.is_empty() {
+				vec!["*"]
+			} else {
+				search.attributes.keys().map(String::as_str).collect()
+			};
+
+			let (entries, _) = ldap
+				.search(&search.base, scope, &search.filter, attributes)
+				.await?
+				.success()
+				.map_err(|e| {
+					check_error!(
+						"Search failed",
+						format!(
+							"Search for '{}' under '{}' failed: {}",
+							search.filter, search.base, e
+						)
+					)
+				})?;
+			let entries: Vec<SearchEntry> =
+				entries.into_iter().map(SearchEntry::construct).collect();
+			verify_entries(search, &entries)?;
+		}
+
+		let _ = ldap.unbind().await;
+		Ok(())
+	}
+}
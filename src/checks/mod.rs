@@ -1,17 +1,101 @@
+mod conn;
+mod content;
+mod dns;
 pub mod errors;
+mod exec;
+mod ftp;
 mod http;
+mod icmp;
+mod imap;
+mod ldap;
+mod pop3;
+mod smb;
+mod smtp;
+mod sql;
 mod ssh;
+mod tcp;
 
-use crate::config::Vm;
+use crate::config::{Config, Service, Vm};
 use async_trait::async_trait;
 use enum_dispatch::enum_dispatch;
-use std::net::Ipv4Addr;
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv4Addr},
+};
 
 pub(crate) use errors::check_bail;
 pub use errors::CheckResult;
 
+// fails at startup on what would otherwise score every team down each round,
+// such as a missing system dependency
+pub fn preflight(cfg: &Config) -> anyhow::Result<()> {
+	for (vm_alias, vm) in &cfg.vms {
+		for (service_alias, service) in &vm.services {
+			let result = match &service.check {
+				Service::Smb(_) => smb::preflight(),
+				// hostnames are only resolved when checked, so assume IPv4 for them
+				Service::Icmp(_) => cfg.teams.keys().try_for_each(|team_alias| {
+					let ip = cfg
+						.address(team_alias, vm_alias)?
+						.parse()
+						.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+					icmp::preflight(ip)
+				}),
+				_ => Ok(()),
+			};
+			result.map_err(|e| {
+				anyhow::anyhow!(
+					"service '{}' on vm '{}' can't be checked: {}",
+					service_alias,
+					vm_alias,
+					e
+				)
+			})?;
+		}
+	}
+
+	Ok(())
+}
+
+// passwords a team has submitted through PCRs, keyed by (vm, service, user)
+pub type Passwords = HashMap<(String, String, String), String>;
+
+// the team's instance of a service that a check is run against
+#[derive(Clone, Copy)]
+pub struct Target<'a> {
+	// resolved from `host` when the check starts
+	pub ip: IpAddr,
+	// as configured, either an IP or a hostname
+	pub host: &'a str,
+	pub team: &'a str,
+	pub vm_alias: &'a str,
+	pub vm: &'a Vm,
+	pub service: &'a str,
+	pub passwords: &'a Passwords,
+}
+
+impl Target<'_> {
+	// the team's current password for `user`, or the configured default if it
+	// was never changed
+	pub fn password<'p>(&'p self, user: &str, default: &'p str) -> &'p str {
+		self.passwords
+			.get(&(
+				self.vm_alias.to_owned(),
+				self.service.to_owned(),
+				user.to_owned(),
+			))
+			.map_or(default, String::as_str)
+	}
+}
+
 #[async_trait]
 #[enum_dispatch(Service)]
 pub trait Check {
-	async fn score(&self, ip: Ipv4Addr, vm: &Vm) -> CheckResult;
+	async fn score(&self, target: &Target<'_>) -> CheckResult;
+
+	// checks that award their own points override the configured up/down
+	// differential
+	async fn score_with_points(&self, target: &Target<'_>) -> (CheckResult, Option<i32>) {
+		(self.score(target).await, None)
+	}
 }
@@ -0,0 +1,126 @@
+use crate::{
+	checks::{
+		check_bail,
+		conn::{self, Conn},
+		smtp, Check, CheckResult, Target,
+	},
+	config::check_types::{MailTls, Pop3},
+};
+use async_trait::async_trait;
+
+// messages searched for a delivered token, newest first
+const SEARCH_DEPTH: usize = 10;
+
+struct Session {
+	conn: Conn,
+}
+
+impl Session {
+	async fn open(cfg: &Pop3, target: &Target<'_>) -> CheckResult<Self> {
+		let password = target.password(&cfg.auth.user, &cfg.auth.password);
+		let port = cfg.port.unwrap_or(match cfg.tls {
+			MailTls::Implicit => 995,
+			MailTls::None | MailTls::Starttls => 110,
+		});
+		let mut session = Self {
+			conn: Conn::open(target.ip, port, cfg.tls == MailTls::Implicit).await?,
+		};
+		session.expect_ok("connect", "Connection failed").await?;
+
+		if cfg.tls == MailTls::Starttls {
+			session.command("STLS", "TLS negotiation failed").await?;
+			session.conn = session.conn.upgrade().await?;
+		}
+
+		session
+			.command(&format!("USER {}", cfg.auth.user), "Authentication failed")
+			.await?;
+		session
+			.command(&format!("PASS {}", password), "Authentication failed")
+			.await?;
+
+		Ok(session)
+	}
+
+	async fn expect_ok(&mut self, command: &str, short: &'static str) -> CheckResult<String> {
+		let line = self.conn.read_line().await?;
+		if !line.starts_with("+OK") {
+			check_bail!(
+				short,
+				format!("'{}' failed: got '{}'", conn::command_name(command), line)
+			);
+		}
+		Ok(line)
+	}
+
+	async fn command(&mut self, command: &str, short: &'static str) -> CheckResult<String> {
+		self.conn.send_line(command).await?;
+		self.expect_ok(command, short).await
+	}
+
+	// message count of the maildrop
+	async fn stat(&mut self) -> CheckResult<usize> {
+		let line = self.command("STAT", "Protocol error").await?;
+		let Some(count) = line.split(' ').nth(1).and_then(|count| count.parse().ok()) else {
+			check_bail!(
+				"Protocol error",
+				format!("Malformed STAT reply: '{}'", line)
+			);
+		};
+		Ok(count)
+	}
+
+	async fn retrieve(&mut self, message: usize) -> CheckResult<String> {
+		self.command(&format!("RETR {}", message), "Retrieval failed")
+			.await?;
+
+		let mut lines = Vec::new();
+		loop {
+			let line = self.conn.read_line().await?;
+			if line == "." {
+				break;
+			}
+			// byte-stuffed lines begin with an extra '.'
+			lines.push(line.strip_prefix('.').unwrap_or(&line).to_owned());
+		}
+		Ok(lines.join("\n"))
+	}
+
+	// deletions only take effect once the server acknowledges QUIT
+	async fn quit(mut self) {
+		let _ = self.command("QUIT", "Protocol error").await;
+	}
+}
+
+#[async_trait]
+impl Check for Pop3 {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let Some(smtp_alias) = &self.confirm_delivery else {
+			let mut session = Session::open(self, target).await?;
+			let count = session.stat().await?;
+			if self.fetch && count > 0 {
+				session.retrieve(count).await?;
+			}
+			session.quit().await;
+			return Ok(());
+		};
+
+		smtp::confirm_delivery(target, smtp_alias, "the mailbox", |token| async move {
+			// the maildrop is only refreshed on login
+			let mut session = Session::open(self, target).await?;
+			let count = session.stat().await?;
+			for message in (1..=count).rev().take(SEARCH_DEPTH) {
+				if session.retrieve(message).await?.contains(&token) {
+					session
+						.command(&format!("DELE {}", message), "Protocol error")
+						.await?;
+					session.quit().await;
+					return Ok(true);
+				}
+			}
+			session.quit().await;
+			Ok(false)
+		})
+		.await
+	}
+}
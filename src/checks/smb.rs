@@ -0,0 +1,192 @@
+use crate::{
+	checks::{check_bail, content, errors::check_error, Check, CheckResult, Target},
+	config::check_types::{FileCheck, Smb},
+};
+use async_trait::async_trait;
+use log::debug;
+use rand::{distributions::Alphanumeric, Rng};
+use std::{path::PathBuf, process::Stdio};
+use tokio::process::Command;
+
+// there is no native SMB client for rust worth depending on, so checks drive
+// `smbclient`, which must be installed on the scoring host
+
+pub fn preflight() -> anyhow::Result<()> {
+	std::process::Command::new("smbclient")
+		.arg("--version")
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.map_err(|e| anyhow::anyhow!("failed to run smbclient: {}", e))?;
+	Ok(())
+}
+
+// local scratch file, removed even if the check is cancelled
+struct TempFile(PathBuf);
+
+impl TempFile {
+	fn new() -> Self {
+		let name: String = rand::thread_rng()
+			.sample_iter(&Alphanumeric)
+			.take(16)
+			.map(char::from)
+			.collect();
+		Self(std::env::temp_dir().join(format!("cobalt-smb-{}", name)))
+	}
+}
+
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.0);
+	}
+}
+
+fn nt_status(output: &str) -> Option<&str> {
+	output
+		.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+		.find(|word| word.starts_with("NT_STATUS_") && *word != "NT_STATUS_OK")
+}
+
+impl Smb {
+	// runs `;`-separated smbclient commands against the share
+	async fn smbclient(&self, target: &Target<'_>, commands: &str) -> CheckResult {
+		let mut cmd = Command::new("smbclient");
+		// the name is what the server sees, the address is what gets dialed
+		cmd.arg(format!("//{}/{}", target.host, self.share))
+			.arg("--ip-address")
+			.arg(target.ip.to_string())
+			.arg("--port")
+			.arg(self.port.to_string())
+			.arg("--command")
+			.arg(commands)
+			.stdin(Stdio::null())
+			.kill_on_drop(true);
+		match &self.auth {
+			Some(auth) => {
+				// read from the environment so it doesn't show up in the process list
+				cmd.arg("--user")
+					.arg(&auth.user)
+					.env("PASSWD", target.password(&auth.user, &auth.password));
+				if let Some(domain) = &auth.domain {
+					cmd.arg("--workgroup").arg(domain);
+				}
+			}
+			None => {
+				cmd.arg("--user").arg("").arg("--no-pass");
+			}
+		}
+
+		let output = cmd.output().await.map_err(|e| {
+			check_error!(
+				"Internal server error",
+				format!("Failed to run smbclient: {}", e)
+			)
+		})?;
+		let text = format!(
+			"{}{}",
+			String::from_utf8_lossy(&output.stdout),
+			String::from_utf8_lossy(&output.stderr)
+		);
+		let status = nt_status(&text);
+		if status.is_none() && output.status.success() {
+			return Ok(());
+		}
+
+		let short = match status {
+			Some(
+				"NT_STATUS_LOGON_FAILURE"
+				| "NT_STATUS_WRONG_PASSWORD"
+				| "NT_STATUS_NO_SUCH_USER"
+				| "NT_STATUS_ACCOUNT_DISABLED"
+				| "NT_STATUS_ACCOUNT_LOCKED_OUT"
+				| "NT_STATUS_ACCOUNT_RESTRICTION"
+				| "NT_STATUS_PASSWORD_EXPIRED",
+			) => "Authentication failed",
+			Some("NT_STATUS_BAD_NETWORK_NAME") => "Share not found",
+			Some(
+				"NT_STATUS_OBJECT_NAME_NOT_FOUND"
+				| "NT_STATUS_OBJECT_PATH_NOT_FOUND"
+				| "NT_STATUS_NO_SUCH_FILE",
+			) => "File not found",
+			Some("NT_STATUS_ACCESS_DENIED") => "Access denied",
+			Some(
+				"NT_STATUS_CONNECTION_REFUSED"
+				| "NT_STATUS_CONNECTION_RESET"
+				| "NT_STATUS_CONNECTION_DISCONNECTED"
+				| "NT_STATUS_HOST_UNREACHABLE"
+				| "NT_STATUS_NETWORK_UNREACHABLE"
+				| "NT_STATUS_IO_TIMEOUT",
+			) => "Connection failed",
+			_ => "Transfer failed",
+		};
+		check_bail!(
+			short,
+			format!(
+				"'{}' on //{}/{} failed: {}",
+				commands,
+				target.host,
+				self.share,
+				text.trim()
+			)
+		);
+	}
+
+	async fn check_file(&self, target: &Target<'_>, file: &FileCheck) -> CheckResult {
+		match file {
+			FileCheck::Download { path, sha256 } => {
+				let local = TempFile::new();
+				self.smbclient(
+					target,
+					&format!("get \"{}\" \"{}\"", path, local.0.display()),
+				)
+				.await?;
+				if let Some(expected) = sha256 {
+					let contents = tokio::fs::read(&local.0).await?;
+					content::verify_sha256(path, &contents, expected)?;
+				}
+			}
+			FileCheck::Upload { path } => {
+				let (written, read_back) = (TempFile::new(), TempFile::new());
+				let contents = content::random_contents();
+				tokio::fs::write(&written.0, &contents).await?;
+				self.smbclient(
+					target,
+					&format!(
+						"put \"{}\" \"{}\"; get \"{}\" \"{}\"",
+						written.0.display(),
+						path,
+						path,
+						read_back.0.display()
+					),
+				)
+				.await?;
+
+				// cleanup is best-effort and doesn't affect the result
+				if let Err(e) = self.smbclient(target, &format!("del \"{}\"", path)).await {
+					debug!("Failed to delete '{}' after upload: {}", path, e.long);
+				}
+
+				let read_back = tokio::fs::read(&read_back.0).await?;
+				content::verify_round_trip(path, &contents, &read_back)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[async_trait]
+impl Check for Smb {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		if self.files.is_empty() {
+			// connecting to the share is enough to verify access
+			return self.smbclient(target, "pwd").await;
+		}
+
+		for file in &self.files {
+			self.check_file(target, file).await?;
+		}
+		Ok(())
+	}
+}
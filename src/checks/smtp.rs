@@ -0,0 +1,171 @@
+use crate::{
+	checks::{check_bail, conn::Conn, errors::check_error, Check, CheckResult, Target},
+	config::{
+		check_types::{MailTls, Smtp},
+		Service,
+	},
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, Rng};
+use std::{future::Future, time::Duration};
+
+// times a mailbox is searched for a freshly sent token before giving up
+const DELIVERY_ATTEMPTS: u32 = 5;
+// delay between searches, since delivery is asynchronous
+const DELIVERY_INTERVAL: Duration = Duration::from_secs(2);
+
+fn auth_mechanisms(ehlo: &str) -> Vec<String> {
+	ehlo.lines()
+		.filter_map(|line| line.get(4..))
+		.filter_map(|line| line.strip_prefix("AUTH"))
+		.flat_map(|mechanisms| mechanisms.split([' ', '=']))
+		.filter(|mechanism| !mechanism.is_empty())
+		.map(str::to_uppercase)
+		.collect()
+}
+
+impl Smtp {
+	fn port(&self) -> u16 {
+		self.port.unwrap_or(match self.tls {
+			MailTls::Implicit => 465,
+			MailTls::None | MailTls::Starttls => 25,
+		})
+	}
+
+	// sends a message carrying a unique token in its subject and body,
+	// returning the token
+	pub async fn send(&self, target: &Target<'_>) -> CheckResult<String> {
+		let mut conn = Conn::open(target.ip, self.port(), self.tls == MailTls::Implicit).await?;
+		conn.expect_reply("connect", &[220], "Connection failed")
+			.await?;
+
+		let ehlo = format!("EHLO {}", self.helo);
+		let mut capabilities = conn.command(&ehlo, &[250], "Protocol error").await?;
+		if self.tls == MailTls::Starttls {
+			conn.command("STARTTLS", &[220], "TLS negotiation failed")
+				.await?;
+			conn = conn.upgrade().await?;
+			capabilities = conn.command(&ehlo, &[250], "Protocol error").await?;
+		}
+
+		if let Some(auth) = &self.auth {
+			let password = target.password(&auth.user, &auth.password);
+			let mechanisms = auth_mechanisms(&capabilities);
+			// PLAIN is assumed if the server doesn't advertise anything
+			if mechanisms.is_empty() || mechanisms.iter().any(|m| m == "PLAIN") {
+				let credentials = STANDARD.encode(format!("\0{}\0{}", auth.user, password));
+				conn.command(
+					&format!("AUTH PLAIN {}", credentials),
+					&[235],
+					"Authentication failed",
+				)
+				.await?;
+			} else if mechanisms.iter().any(|m| m == "LOGIN") {
+				conn.command("AUTH LOGIN", &[334], "Authentication failed")
+					.await?;
+				// replies refer to the AUTH command, not the encoded credentials
+				conn.send_line(&STANDARD.encode(&auth.user)).await?;
+				conn.expect_reply("AUTH LOGIN", &[334], "Authentication failed")
+					.await?;
+				conn.send_line(&STANDARD.encode(password)).await?;
+				conn.expect_reply("AUTH LOGIN", &[235], "Authentication failed")
+					.await?;
+			} else {
+				check_bail!(
+					"Authentication failed",
+					format!("No supported AUTH mechanism among {:?}", mechanisms)
+				);
+			}
+		}
+
+		let token = format!(
+			"cobalt-{}",
+			rand::thread_rng()
+				.sample_iter(&Alphanumeric)
+				.take(16)
+				.map(char::from)
+				.collect::<String>()
+		);
+
+		conn.command(&format!("MAIL FROM:<{}>", self.from), &[250], "Send failed")
+			.await?;
+		conn.command(
+			&format!("RCPT TO:<{}>", self.to),
+			&[250, 251],
+			"Send failed",
+		)
+		.await?;
+		conn.command("DATA", &[354], "Send failed").await?;
+		for line in [
+			format!("From: <{}>", self.from),
+			format!("To: <{}>", self.to),
+			format!("Subject: Scoring check {}", token),
+			format!("Date: {}", Utc::now().to_rfc2822()),
+			String::new(),
+			token.clone(),
+		] {
+			conn.send_line(&line).await?;
+		}
+		conn.command(".", &[250], "Send failed").await?;
+
+		let _ = conn.send_line("QUIT").await;
+		Ok(token)
+	}
+}
+
+// sends a token through the smtp service `alias` on the same vm, for a mail
+// check to look for
+async fn send_token(target: &Target<'_>, alias: &str) -> CheckResult<String> {
+	let Some(Service::Smtp(smtp)) = target.vm.services.get(alias).map(|smtp| &smtp.check) else {
+		// pre-validated
+		return Err(check_error!(
+			"Internal server error",
+			format!("'{}' is not an smtp service", alias)
+		));
+	};
+	smtp.send(&Target {
+		service: alias,
+		..*target
+	})
+	.await
+}
+
+// sends a token through the smtp service `alias`, then runs `search` for it
+// until it reports the token found in `mailbox`
+pub async fn confirm_delivery<F, Fut>(
+	target: &Target<'_>,
+	alias: &str,
+	mailbox: &str,
+	mut search: F,
+) -> CheckResult
+where
+	F: FnMut(String) -> Fut,
+	Fut: Future<Output = CheckResult<bool>>,
+{
+	let token = send_token(target, alias).await?;
+	for attempt in 1..=DELIVERY_ATTEMPTS {
+		if search(token.clone()).await? {
+			return Ok(());
+		}
+		if attempt < DELIVERY_ATTEMPTS {
+			tokio::time::sleep(DELIVERY_INTERVAL).await;
+		}
+	}
+
+	check_bail!(
+		"Mail not delivered",
+		format!(
+			"Token '{}' sent via '{}' did not arrive in {}",
+			token, alias, mailbox
+		)
+	);
+}
+
+#[async_trait]
+impl Check for Smtp {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		self.send(target).await.map(|_| ())
+	}
+}
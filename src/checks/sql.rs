@@ -0,0 +1,168 @@
+use crate::{
+	checks::{check_bail, errors::check_error, errors::CheckError, Check, CheckResult, Target},
+	config::check_types::{Mysql, Postgres, SqlColumn, SqlQuery},
+};
+use async_trait::async_trait;
+use sqlx::{
+	mysql::{MySqlConnectOptions, MySqlConnection},
+	postgres::{PgConnectOptions, PgConnection},
+	Column, ColumnIndex, ConnectOptions, Connection, Database, Decode, Executor, Row,
+};
+use std::net::{IpAddr, SocketAddr};
+
+// rows rendered as text, with `None` for nulls
+struct ResultSet {
+	columns: Vec<String>,
+	rows: Vec<Vec<Option<String>>>,
+}
+
+impl ResultSet {
+	// queries without bind parameters use the simple/text protocol, so every
+	// value can be decoded as a string regardless of its column type
+	fn from_rows<R: Row>(rows: &[R]) -> CheckResult<Self>
+	where
+		for<'r> Option<String>: Decode<'r, R::Database>,
+		usize: ColumnIndex<R>,
+	{
+		let columns = rows
+			.first()
+			.map(|row| {
+				row.columns()
+					.iter()
+					.map(|column| column.name().to_owned())
+					.collect()
+			})
+			.unwrap_or_default();
+		let rows = rows
+			.iter()
+			.map(|row| {
+				(0..row.len())
+					.map(|i| row.try_get_unchecked::<Option<String>, _>(i))
+					.collect::<Result<_, _>>()
+			})
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self { columns, rows })
+	}
+
+	fn cell(&self, row: usize, column: &SqlColumn) -> Option<&Option<String>> {
+		let column = match column {
+			SqlColumn::Index(index) => *index,
+			SqlColumn::Name(name) => self.columns.iter().position(|column| column == name)?,
+		};
+		self.rows.get(row)?.get(column)
+	}
+}
+
+fn connect_error(ip: IpAddr, port: u16, err: sqlx::Error) -> CheckError {
+	let addr = SocketAddr::new(ip, port);
+	match &err {
+		// SQLSTATE class 28: invalid authorization specification
+		sqlx::Error::Database(db_err)
+			if db_err.code().is_some_and(|code| code.starts_with("28")) =>
+		{
+			check_error!(
+				"Authentication failed",
+				format!("Authentication to {} failed: {}", addr, err)
+			)
+		}
+		_ => check_error!(
+			"Connection failed",
+			format!("Failed to connect to {}: {}", addr, err)
+		),
+	}
+}
+
+async fn run_query<C: Connection>(
+	cfg: &SqlQuery,
+	mut conn: C,
+) -> CheckResult<Vec<<C::Database as Database>::Row>>
+where
+	for<'c> &'c mut C: Executor<'c, Database = C::Database>,
+{
+	let rows = conn.fetch_all(cfg.query.as_str()).await.map_err(|e| {
+		check_error!(
+			"Query failed",
+			format!("Query '{}' failed: {}", cfg.query, e)
+		)
+	})?;
+	let _ = conn.close().await;
+	Ok(rows)
+}
+
+fn verify(cfg: &SqlQuery, results: ResultSet) -> CheckResult {
+	if let Some(expected) = cfg.rows {
+		if results.rows.len() != expected {
+			check_bail!(
+				"Row count mismatch",
+				format!(
+					"Query '{}' returned {} rows, expected {}",
+					cfg.query,
+					results.rows.len(),
+					expected
+				)
+			);
+		}
+	}
+
+	if let Some(cell) = &cfg.cell {
+		let Some(value) = results.cell(cell.row, &cell.column) else {
+			check_bail!(
+				"Missing cell",
+				format!(
+					"Query '{}' returned no cell at row {}, column {}",
+					cfg.query, cell.row, cell.column
+				)
+			);
+		};
+		cell.value.verify(value.as_deref().unwrap_or("NULL"))?;
+	}
+
+	Ok(())
+}
+
+#[async_trait]
+impl Check for Mysql {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let (cfg, ip) = (&self.0, target.ip);
+		let port = cfg.port.unwrap_or(3306);
+		let mut options = MySqlConnectOptions::new()
+			.host(&ip.to_string())
+			.port(port)
+			.username(&cfg.user)
+			.password(target.password(&cfg.user, &cfg.password));
+		if let Some(database) = &cfg.database {
+			options = options.database(database);
+		}
+
+		let conn: MySqlConnection = options
+			.connect()
+			.await
+			.map_err(|e| connect_error(ip, port, e))?;
+		let rows = run_query(cfg, conn).await?;
+		verify(cfg, ResultSet::from_rows(&rows)?)
+	}
+}
+
+#[async_trait]
+impl Check for Postgres {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let (cfg, ip) = (&self.0, target.ip);
+		let port = cfg.port.unwrap_or(5432);
+		let mut options = PgConnectOptions::new()
+			.host(&ip.to_string())
+			.port(port)
+			.username(&cfg.user)
+			.password(target.password(&cfg.user, &cfg.password));
+		if let Some(database) = &cfg.database {
+			options = options.database(database);
+		}
+
+		let conn: PgConnection = options
+			.connect()
+			.await
+			.map_err(|e| connect_error(ip, port, e))?;
+		let rows = run_query(cfg, conn).await?;
+		verify(cfg, ResultSet::from_rows(&rows)?)
+	}
+}
@@ -1,24 +1,55 @@
 use crate::{
-	checks::{Check, CheckResult},
-	config::{
-		check_types::{Ssh, SshAuthType},
-		Vm,
+	checks::{
+		check_bail, conn,
+		errors::{check_error, CheckError},
+		Check, CheckResult, Target,
 	},
+	config::check_types::{Ssh, SshAuthType},
 };
+use anyhow::anyhow;
 use async_trait::async_trait;
-use std::{io::Read, net::Ipv4Addr};
-use tokio::net::TcpStream;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use ssh2::HashType;
+use std::{
+	io::Read,
+	net::{Shutdown, TcpStream},
+};
 
-use super::check_bail;
+// ssh2 is blocking, so a cancelled check (e.g. timed out) would otherwise leave
+// its worker thread stuck on the socket; shutting it down fails any pending
+// call immediately
+struct ShutdownOnDrop(TcpStream);
 
-#[async_trait]
-impl Check for Ssh {
-	async fn score(&self, ip: Ipv4Addr, _: &Vm) -> CheckResult {
-		let stream = TcpStream::connect((ip, self.port)).await?;
+impl Drop for ShutdownOnDrop {
+	fn drop(&mut self) {
+		let _ = self.0.shutdown(Shutdown::Both);
+	}
+}
 
+impl Ssh {
+	fn run(&self, stream: TcpStream) -> CheckResult {
 		let mut sess = ssh2::Session::new()?;
 		sess.set_tcp_stream(stream);
-		sess.handshake()?;
+		sess.handshake().map_err(|e| {
+			check_error!("Handshake failed", format!("SSH handshake failed: {}", e))
+		})?;
+
+		if let Some(expected) = &self.host_key {
+			let fingerprint = sess
+				.host_key_hash(HashType::Sha256)
+				.map(|hash| format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)));
+			if fingerprint.as_deref() != Some(expected.as_str()) {
+				check_bail!(
+					"Host key mismatch",
+					format!(
+						"Host key mismatch: expected '{}', got '{}'",
+						expected,
+						fingerprint.unwrap_or_default()
+					)
+				);
+			}
+		}
+
 		match &self.auth {
 			SshAuthType::Password { user, password } => sess.userauth_password(user, password),
 			SshAuthType::Pubkey {
@@ -26,9 +57,20 @@ impl Check for Ssh {
 				private_key,
 				passphrase,
 			} => sess.userauth_pubkey_file(user, None, private_key, passphrase.as_deref()),
-		}?;
+		}
+		.map_err(|e| {
+			check_error!(
+				"Authentication failed",
+				format!("SSH authentication failed: {}", e)
+			)
+		})?;
 
-		if let Some(command) = &self.command {
+		let commands = self
+			.command
+			.iter()
+			.map(|command| (command, &self.output))
+			.chain(self.commands.iter().map(|c| (&c.command, &c.output)));
+		for (command, output) in commands {
 			let mut channel = sess.channel_session()?;
 			channel.exec(command)?;
 			let mut res = String::new();
@@ -45,8 +87,27 @@ impl Check for Ssh {
 					)
 				);
 			}
+
+			output.verify(&res).map_err(|e| {
+				CheckError::new(e.short, anyhow!("command '{}': {}", command, e.long))
+			})?;
 		}
 
 		Ok(())
 	}
 }
+
+#[async_trait]
+impl Check for Ssh {
+	async fn score(&self, target: &Target<'_>) -> CheckResult {
+		let stream = conn::connect(target.ip, self.port).await?.into_std()?;
+		stream.set_nonblocking(false)?;
+		let _guard = ShutdownOnDrop(stream.try_clone()?);
+
+		let mut ssh = self.clone();
+		if let SshAuthType::Password { user, password } = &mut ssh.auth {
+			*password = target.password(user, password).to_owned();
+		}
+		tokio::task::spawn_blocking(move || ssh.run(stream)).await?
+	}
+}
@@ -0,0 +1,49 @@
+use crate::{
+	checks::{conn, Check, CheckResult, Target},
+	config::check_types::Tcp,
+};
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	time::timeout,
+};
+
+// the banner is considered complete once the server goes quiet for this long
+const BANNER_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+// most banner bytes read before matching
+const MAX_BANNER_LEN: usize = 64 * 1024;
+
+#[async_trait]
+impl Check for Tcp {
+	async fn score(&self, &Target { ip, .. }: &Target<'_>) -> CheckResult {
+		let mut stream = conn::connect(ip, self.port).await?;
+		if let Some(send) = &self.send {
+			stream.write_all(send.as_bytes()).await?;
+			stream.flush().await?;
+		}
+
+		let banner = &self.banner;
+		if banner.contains.is_none() && banner.contains_regex.is_none() && banner.equals.is_none() {
+			return Ok(());
+		}
+
+		// read until the expectations are met, the server closes the connection
+		// or stops sending
+		let mut received = Vec::new();
+		let mut buf = [0; 4096];
+		loop {
+			let text = String::from_utf8_lossy(&received);
+			if banner.verify(&text).is_ok() {
+				return Ok(());
+			}
+
+			match timeout(BANNER_IDLE_TIMEOUT, stream.read(&mut buf)).await {
+				Ok(Ok(read)) if read > 0 && received.len() < MAX_BANNER_LEN => {
+					received.extend_from_slice(&buf[..read]);
+				}
+				_ => return banner.verify(&text),
+			}
+		}
+	}
+}
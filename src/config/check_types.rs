@@ -1,5 +1,5 @@
 use regex::Regex;
-use serde::Deserialize;
+use serde::{de::Error, Deserialize, Deserializer};
 use serde_with::{serde_as, DisplayFromStr};
 use std::{collections::HashMap, path::PathBuf};
 
@@ -7,19 +7,183 @@ fn default_http_method() -> reqwest::Method {
 	reqwest::Method::GET
 }
 
+// expectations on the content returned by a check (response body, command output)
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ContentMatch {
+	pub contains: Option<String>,
+	#[serde(default)]
+	#[serde(with = "serde_regex")]
+	pub contains_regex: Option<Regex>,
+	// exact match, ignoring trailing newlines
+	pub equals: Option<String>,
+}
+
+impl ContentMatch {
+	// whether there is nothing to check
+	pub fn is_empty(&self) -> bool {
+		self.contains.is_none() && self.contains_regex.is_none() && self.equals.is_none()
+	}
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ExpectedStatus {
+	Single(u16),
+	List(Vec<u16>),
+	Range { min: u16, max: u16 },
+}
+
+impl ExpectedStatus {
+	pub fn matches(&self, status: u16) -> bool {
+		match self {
+			Self::Single(code) => *code == status,
+			Self::List(codes) => codes.contains(&status),
+			Self::Range { min, max } => (*min..=*max).contains(&status),
+		}
+	}
+}
+
+impl std::fmt::Display for ExpectedStatus {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Single(code) => write!(f, "{}", code),
+			Self::List(codes) => write!(f, "one of {:?}", codes),
+			Self::Range { min, max } => write!(f, "{}-{}", min, max),
+		}
+	}
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpScheme {
+	#[default]
+	Http,
+	Https,
+}
+
+impl HttpScheme {
+	pub fn default_port(&self) -> u16 {
+		match self {
+			Self::Http => 80,
+			Self::Https => 443,
+		}
+	}
+}
+
+impl std::fmt::Display for HttpScheme {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Http => write!(f, "http"),
+			Self::Https => write!(f, "https"),
+		}
+	}
+}
+
+fn default_tls_verify() -> bool {
+	true
+}
+
+// a PEM bundle of CA certificates, read once when the config is loaded
+#[derive(Debug)]
+pub struct CaBundle {
+	pub path: PathBuf,
+	pub certs: Vec<reqwest::Certificate>,
+}
+
+impl<'de> Deserialize<'de> for CaBundle {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		let path = PathBuf::deserialize(deserializer)?;
+		let load = || -> anyhow::Result<Vec<reqwest::Certificate>> {
+			let pem = std::fs::read(&path)?;
+			let certs = openssl::x509::X509::stack_from_pem(&pem)?
+				.iter()
+				.map(|cert| Ok(reqwest::Certificate::from_der(&cert.to_der()?)?))
+				.collect::<anyhow::Result<Vec<_>>>()?;
+			anyhow::ensure!(!certs.is_empty(), "no certificates found");
+			Ok(certs)
+		};
+
+		match load() {
+			Ok(certs) => Ok(Self { path, certs }),
+			Err(e) => Err(D::Error::custom(format!(
+				"failed to load CA bundle '{}': {}",
+				path.display(),
+				e
+			))),
+		}
+	}
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TlsOptions {
+	#[serde(default = "default_tls_verify")]
+	pub verify: bool,
+	// trust only the certificates in this PEM bundle instead of the system roots
+	pub ca_bundle: Option<CaBundle>,
+	// must appear as the certificate's CN or one of its DNS SANs
+	pub expected_name: Option<String>,
+	pub min_days_to_expiry: Option<u32>,
+}
+
+impl Default for TlsOptions {
+	fn default() -> Self {
+		Self {
+			verify: default_tls_verify(),
+			ca_bundle: None,
+			expected_name: None,
+			min_days_to_expiry: None,
+		}
+	}
+}
+
+fn default_capture_group() -> usize {
+	1
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "from")]
+#[serde(rename_all = "snake_case")]
+pub enum Capture {
+	Regex {
+		#[serde(with = "serde_regex")]
+		regex: Regex,
+		#[serde(default = "default_capture_group")]
+		group: usize,
+	},
+	JsonPointer {
+		pointer: String,
+	},
+	Header {
+		name: String,
+	},
+}
+
 #[serde_as]
 #[derive(Deserialize, Debug)]
 pub struct HttpInner {
+	#[serde(default)]
+	pub scheme: HttpScheme,
+	// defaults to the scheme's well-known port
+	pub port: Option<u16>,
+	// hostname sent in the SNI extension and `Host` header in place of the IP
+	pub host: Option<String>,
+	#[serde(default)]
+	pub tls: TlsOptions,
 	#[serde(default = "default_http_method")]
 	#[serde_as(as = "DisplayFromStr")]
 	pub method: reqwest::Method,
 	pub path: String,
 	pub headers: Option<HashMap<String, String>>,
 	pub body: Option<String>,
-	pub contains: Option<String>,
-	#[serde(default)]
-	#[serde(with = "serde_regex")]
-	pub contains_regex: Option<Regex>,
+	#[serde(flatten)]
+	pub content: ContentMatch,
+	// any status is accepted if unset
+	pub expected_status: Option<ExpectedStatus>,
+	// values made available to later pages as `{{name}}`
+	pub captures: Option<HashMap<String, Capture>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -27,7 +191,7 @@ pub struct Http {
 	pub pages: Vec<HttpInner>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(tag = "method")]
 #[serde(rename_all = "snake_case")]
 pub enum SshAuthType {
@@ -46,10 +210,394 @@ fn default_ssh_port() -> u16 {
 	22
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
+pub struct SshCommand {
+	pub command: String,
+	#[serde(flatten)]
+	pub output: ContentMatch,
+}
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct Ssh {
 	#[serde(default = "default_ssh_port")]
 	pub port: u16,
 	pub auth: SshAuthType,
 	pub command: Option<String>,
+	// expectations on the output of `command`
+	#[serde(flatten)]
+	pub output: ContentMatch,
+	// run in order after `command`, each with its own expectations
+	#[serde(default)]
+	pub commands: Vec<SshCommand>,
+	// OpenSSH-style SHA256 fingerprint, e.g. `SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8`
+	pub host_key: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+	A,
+	Aaaa,
+	Mx,
+	Txt,
+	Cname,
+	Ptr,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsProtocol {
+	Udp,
+	Tcp,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DnsRecord {
+	// an IP address may be given for PTR records
+	pub name: String,
+	#[serde(rename = "type")]
+	pub record_type: DnsRecordType,
+	// every expected answer must be present; any answer is accepted if empty
+	#[serde(default)]
+	pub expected: Vec<String>,
+}
+
+fn default_dns_port() -> u16 {
+	53
+}
+
+fn default_dns_protocols() -> Vec<DnsProtocol> {
+	vec![DnsProtocol::Udp, DnsProtocol::Tcp]
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Dns {
+	#[serde(default = "default_dns_port")]
+	pub port: u16,
+	#[serde(default = "default_dns_protocols")]
+	pub protocols: Vec<DnsProtocol>,
+	pub records: Vec<DnsRecord>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(tag = "method")]
+#[serde(rename_all = "snake_case")]
+pub enum FtpAuthType {
+	#[default]
+	Anonymous,
+	Password {
+		user: String,
+		password: String,
+	},
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FtpMode {
+	#[default]
+	Passive,
+	Active,
+}
+
+// file transfers checked over FTP and SMB
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "action")]
+#[serde(rename_all = "snake_case")]
+pub enum FileCheck {
+	// fetch an existing file, optionally verifying its SHA-256 hash
+	Download {
+		path: String,
+		sha256: Option<String>,
+	},
+	// store a random file, read it back and delete it
+	Upload {
+		path: String,
+	},
+}
+
+fn default_ftp_port() -> u16 {
+	21
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Ftp {
+	#[serde(default = "default_ftp_port")]
+	pub port: u16,
+	#[serde(default)]
+	pub auth: FtpAuthType,
+	#[serde(default)]
+	pub mode: FtpMode,
+	// explicit TLS (`AUTH TLS`); certificates are not verified
+	#[serde(default)]
+	pub tls: bool,
+	#[serde(default)]
+	pub files: Vec<FileCheck>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MailTls {
+	#[default]
+	None,
+	// upgrade a plaintext session (`STARTTLS` / `STLS`)
+	Starttls,
+	// TLS from the first byte, as on ports 465, 993 and 995
+	Implicit,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct MailCredentials {
+	pub user: String,
+	pub password: String,
+}
+
+fn default_smtp_helo() -> String {
+	"cobalt.local".to_owned()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Smtp {
+	// defaults to 465 with implicit TLS, 25 otherwise
+	pub port: Option<u16>,
+	#[serde(default)]
+	pub tls: MailTls,
+	#[serde(default = "default_smtp_helo")]
+	pub helo: String,
+	pub auth: Option<MailCredentials>,
+	pub from: String,
+	pub to: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Pop3 {
+	// defaults to 995 with implicit TLS, 110 otherwise
+	pub port: Option<u16>,
+	#[serde(default)]
+	pub tls: MailTls,
+	pub auth: MailCredentials,
+	// retrieve the newest message
+	#[serde(default)]
+	pub fetch: bool,
+	// smtp service on the same vm used to send a token that must then arrive
+	// in this mailbox
+	pub confirm_delivery: Option<String>,
+}
+
+fn default_imap_mailbox() -> String {
+	"INBOX".to_owned()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Imap {
+	// defaults to 993 with implicit TLS, 143 otherwise
+	pub port: Option<u16>,
+	#[serde(default)]
+	pub tls: MailTls,
+	pub auth: MailCredentials,
+	#[serde(default = "default_imap_mailbox")]
+	pub mailbox: String,
+	// fetch the headers of the newest message
+	#[serde(default)]
+	pub fetch: bool,
+	// as for `Pop3`
+	pub confirm_delivery: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum SqlColumn {
+	Index(usize),
+	Name(String),
+}
+
+impl Default for SqlColumn {
+	fn default() -> Self {
+		Self::Index(0)
+	}
+}
+
+impl std::fmt::Display for SqlColumn {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Index(index) => write!(f, "{}", index),
+			Self::Name(name) => write!(f, "'{}'", name),
+		}
+	}
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SqlCell {
+	// zero-based
+	#[serde(default)]
+	pub row: usize,
+	// zero-based index or column name
+	#[serde(default)]
+	pub column: SqlColumn,
+	// expectations on the cell's text representation (`NULL` if null)
+	#[serde(flatten)]
+	pub value: ContentMatch,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SqlQuery {
+	// defaults to the database's well-known port
+	pub port: Option<u16>,
+	pub user: String,
+	pub password: String,
+	pub database: Option<String>,
+	pub query: String,
+	// exact number of rows returned
+	pub rows: Option<usize>,
+	pub cell: Option<SqlCell>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(transparent)]
+pub struct Mysql(pub SqlQuery);
+
+#[derive(Deserialize, Debug)]
+#[serde(transparent)]
+pub struct Postgres(pub SqlQuery);
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LdapTls {
+	#[default]
+	None,
+	Starttls,
+	Ldaps,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LdapScope {
+	Base,
+	OneLevel,
+	#[default]
+	Subtree,
+}
+
+fn default_ldap_filter() -> String {
+	"(objectClass=*)".to_owned()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LdapSearch {
+	pub base: String,
+	#[serde(default = "default_ldap_filter")]
+	pub filter: String,
+	#[serde(default)]
+	pub scope: LdapScope,
+	// DNs that must be returned; at least one entry is required if this and
+	// `attributes` are both empty
+	#[serde(default)]
+	pub entries: Vec<String>,
+	// each attribute must be present on some returned entry with every listed
+	// value
+	#[serde(default)]
+	pub attributes: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Ldap {
+	// defaults to 636 for LDAPS, 389 otherwise
+	pub port: Option<u16>,
+	// certificates are not verified
+	#[serde(default)]
+	pub tls: LdapTls,
+	pub bind_dn: String,
+	pub password: String,
+	pub search: Option<LdapSearch>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SmbCredentials {
+	pub user: String,
+	pub password: String,
+	pub domain: Option<String>,
+}
+
+fn default_smb_port() -> u16 {
+	445
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Smb {
+	#[serde(default = "default_smb_port")]
+	pub port: u16,
+	pub share: String,
+	// connects as guest if unset
+	pub auth: Option<SmbCredentials>,
+	#[serde(default)]
+	pub files: Vec<FileCheck>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Tcp {
+	pub port: u16,
+	// written as soon as the connection is established
+	pub send: Option<String>,
+	// expectations on whatever the server sends back; an open port is enough
+	// if unset
+	#[serde(flatten)]
+	pub banner: ContentMatch,
+}
+
+fn default_icmp_count() -> u16 {
+	3
+}
+
+fn default_icmp_max_loss() -> u8 {
+	50
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Icmp {
+	#[serde(default = "default_icmp_count")]
+	pub count: u16,
+	// highest tolerated packet loss, in percent
+	#[serde(default = "default_icmp_max_loss")]
+	pub max_loss: u8,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Exec {
+	pub command: PathBuf,
+	#[serde(default)]
+	pub args: Vec<String>,
+	// passed through to the script as-is
+	#[serde(default)]
+	pub params: serde_json::Map<String, serde_json::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn expected(yaml: &str) -> ExpectedStatus {
+		serde_yaml::from_str(yaml).unwrap()
+	}
+
+	#[test]
+	fn expected_status_single() {
+		let status = expected("200");
+		assert!(status.matches(200));
+		assert!(!status.matches(201));
+	}
+
+	#[test]
+	fn expected_status_list() {
+		let status = expected("[200, 301, 302]");
+		assert!(status.matches(301));
+		assert!(!status.matches(303));
+	}
+
+	#[test]
+	fn expected_status_range() {
+		let status = expected("{min: 200, max: 299}");
+		assert!(status.matches(200));
+		assert!(status.matches(299));
+		assert!(!status.matches(300));
+		assert!(!status.matches(199));
+	}
 }
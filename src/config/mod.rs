@@ -1,21 +1,37 @@
 pub mod check_types;
 
-use crate::checks::{Check, CheckResult};
+use crate::checks::{Check, CheckResult, Target};
 use anyhow::{bail, ensure};
 use enum_dispatch::enum_dispatch;
+use itertools::Itertools;
 use rand::Rng;
 use serde::Deserialize;
-use std::{collections::HashMap, net::Ipv4Addr, ops::Deref, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, net::IpAddr, ops::Deref, path::PathBuf, sync::Arc};
 
+// scoring time, excluding pauses (default: 4hr)
+const DEFAULT_COMPETITION_LENGTH: i64 = 4 * 60 * 60;
 // check interval (default: 120sec)
 const DEFAULT_INTERVAL: u32 = 120;
 // check jitter min/max (default: 10sec)
 const DEFAULT_JITTER: u32 = 10;
 // check timeout (default: 30sec)
 const DEFAULT_CHECK_TIMEOUT: u32 = 30;
+// check attempts per round before a service is scored down (default: 1)
+const DEFAULT_CHECK_ATTEMPTS: u32 = 1;
+// delay between check attempts (default: 0sec)
+const DEFAULT_CHECK_RETRY_DELAY: u32 = 0;
+
+// checks in flight across all teams (default: 32)
+const DEFAULT_GLOBAL_CONCURRENCY: usize = 32;
+// checks in flight against a single team (default: 4)
+const DEFAULT_TEAM_CONCURRENCY: usize = 4;
 
 // most consecutive downs before SLA is triggered
 const DEFAULT_MAX_CONSECUTIVE_DOWNS: u32 = 5;
+// checks considered by the uptime SLA policy (default: 20)
+const DEFAULT_UPTIME_WINDOW: u32 = 20;
+// uptime percentage below which the uptime SLA policy triggers (default: 80%)
+const DEFAULT_MIN_UPTIME: u32 = 80;
 
 /// SCORING ///
 // service up point differential (default: +5)
@@ -33,15 +49,98 @@ const DEFAULT_WEB_PORT: u16 = 8080;
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 #[enum_dispatch]
+// parsed once at startup, so variant size doesn't matter
+#[allow(clippy::large_enum_variant)]
 pub enum Service {
 	Http(check_types::Http),
 	Ssh(check_types::Ssh),
+	Dns(check_types::Dns),
+	Ftp(check_types::Ftp),
+	Smtp(check_types::Smtp),
+	Pop3(check_types::Pop3),
+	Imap(check_types::Imap),
+	Mysql(check_types::Mysql),
+	Postgres(check_types::Postgres),
+	Ldap(check_types::Ldap),
+	Smb(check_types::Smb),
+	Tcp(check_types::Tcp),
+	Icmp(check_types::Icmp),
+	Exec(check_types::Exec),
+}
+
+impl Service {
+	// the user whose password teams can change through a PCR, if the check
+	// logs in with a password
+	pub fn credentialed_user(&self) -> Option<&str> {
+		match self {
+			Self::Ssh(check_types::Ssh {
+				auth: check_types::SshAuthType::Password { user, .. },
+				..
+			})
+			| Self::Ftp(check_types::Ftp {
+				auth: check_types::FtpAuthType::Password { user, .. },
+				..
+			}) => Some(user),
+			Self::Smtp(smtp) => smtp.auth.as_ref().map(|auth| auth.user.as_str()),
+			Self::Pop3(pop3) => Some(&pop3.auth.user),
+			Self::Imap(imap) => Some(&imap.auth.user),
+			Self::Mysql(check_types::Mysql(query))
+			| Self::Postgres(check_types::Postgres(query)) => Some(&query.user),
+			Self::Ldap(ldap) => Some(&ldap.bind_dn),
+			Self::Smb(smb) => smb.auth.as_ref().map(|auth| auth.user.as_str()),
+			_ => None,
+		}
+	}
+}
+
+// per-service replacements for the global SLA thresholds and `scoring.sla`
+#[derive(Deserialize, Debug, Default)]
+pub struct SlaOverrides {
+	pub max_consecutive_downs: Option<u32>,
+	pub min_uptime: Option<u32>,
+	pub penalty: Option<i32>,
+}
+
+// a check along with overrides of the global check timing and SLAs
+#[derive(Deserialize, Debug)]
+pub struct ServiceConfig {
+	#[serde(flatten)]
+	pub check: Service,
+	pub timeout: Option<u32>,
+	pub attempts: Option<u32>,
+	pub retry_delay: Option<u32>,
+	#[serde(default)]
+	pub sla: SlaOverrides,
+}
+
+impl ServiceConfig {
+	pub fn timeout(&self, timing: &Timing) -> u32 {
+		self.timeout.unwrap_or(timing.check_timeout)
+	}
+
+	pub fn attempts(&self, timing: &Timing) -> u32 {
+		self.attempts.unwrap_or(timing.check_attempts)
+	}
+
+	pub fn retry_delay(&self, timing: &Timing) -> u32 {
+		self.retry_delay.unwrap_or(timing.check_retry_delay)
+	}
+
+	pub fn max_consecutive_downs(&self, slas: &Slas) -> u32 {
+		self.sla
+			.max_consecutive_downs
+			.unwrap_or(slas.max_consecutive_downs)
+	}
+
+	pub fn min_uptime(&self, slas: &Slas) -> u32 {
+		self.sla.min_uptime.unwrap_or(slas.min_uptime)
+	}
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Vm {
 	pub ip: u8,
-	pub services: HashMap<String, Service>,
+	pub services: HashMap<String, ServiceConfig>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -50,7 +149,7 @@ pub struct Vm {
 pub enum InjectType {
 	Service {
 		vm: String,
-		services: HashMap<String, Service>,
+		services: HashMap<String, ServiceConfig>,
 	},
 	Response,
 }
@@ -77,12 +176,49 @@ pub struct Inject {
 fn default_max_consecutive_downs() -> u32 {
 	DEFAULT_MAX_CONSECUTIVE_DOWNS
 }
+fn default_uptime_window() -> u32 {
+	DEFAULT_UPTIME_WINDOW
+}
+fn default_min_uptime() -> u32 {
+	DEFAULT_MIN_UPTIME
+}
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaPolicy {
+	// every `max_consecutive_downs` consecutive downs
+	#[default]
+	Repeating,
+	// once per outage, after `max_consecutive_downs` consecutive downs
+	OneShot,
+	// when uptime over the last `window` checks is below `min_uptime` percent,
+	// at most once per window
+	Uptime,
+}
+
+#[derive(Deserialize, Debug)]
 pub struct Slas {
 	pub enable: bool,
+	#[serde(default)]
+	pub policy: SlaPolicy,
 	#[serde(default = "default_max_consecutive_downs")]
 	pub max_consecutive_downs: u32,
+	#[serde(default = "default_uptime_window")]
+	pub window: u32,
+	#[serde(default = "default_min_uptime")]
+	pub min_uptime: u32,
+}
+
+impl Default for Slas {
+	fn default() -> Self {
+		Self {
+			enable: false,
+			policy: SlaPolicy::default(),
+			max_consecutive_downs: DEFAULT_MAX_CONSECUTIVE_DOWNS,
+			window: DEFAULT_UPTIME_WINDOW,
+			min_uptime: DEFAULT_MIN_UPTIME,
+		}
+	}
 }
 
 fn default_service_up_points() -> i32 {
@@ -107,6 +243,9 @@ pub struct Scoring {
 	pub sla: i32,
 }
 
+fn default_competition_length() -> crate::offset::Offset {
+	chrono::Duration::seconds(DEFAULT_COMPETITION_LENGTH).into()
+}
 fn default_interval() -> u32 {
 	DEFAULT_INTERVAL
 }
@@ -116,15 +255,28 @@ fn default_jitter() -> u32 {
 fn default_check_timeout() -> u32 {
 	DEFAULT_CHECK_TIMEOUT
 }
+fn default_check_attempts() -> u32 {
+	DEFAULT_CHECK_ATTEMPTS
+}
+fn default_check_retry_delay() -> u32 {
+	DEFAULT_CHECK_RETRY_DELAY
+}
 
 #[derive(Deserialize, Debug)]
 pub struct Timing {
+	// total scoring time, excluding pauses
+	#[serde(default = "default_competition_length")]
+	pub competition_length: crate::offset::Offset,
 	#[serde(default = "default_interval")]
 	pub interval: u32,
 	#[serde(default = "default_jitter")]
 	pub jitter: u32,
 	#[serde(default = "default_check_timeout")]
 	pub check_timeout: u32,
+	#[serde(default = "default_check_attempts")]
+	pub check_attempts: u32,
+	#[serde(default = "default_check_retry_delay")]
+	pub check_retry_delay: u32,
 }
 
 impl Timing {
@@ -134,6 +286,30 @@ impl Timing {
 	}
 }
 
+fn default_global_concurrency() -> usize {
+	DEFAULT_GLOBAL_CONCURRENCY
+}
+fn default_team_concurrency() -> usize {
+	DEFAULT_TEAM_CONCURRENCY
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Concurrency {
+	#[serde(default = "default_global_concurrency")]
+	pub global: usize,
+	#[serde(default = "default_team_concurrency")]
+	pub per_team: usize,
+}
+
+impl Default for Concurrency {
+	fn default() -> Self {
+		Self {
+			global: DEFAULT_GLOBAL_CONCURRENCY,
+			per_team: DEFAULT_TEAM_CONCURRENCY,
+		}
+	}
+}
+
 fn default_web_port() -> u16 {
 	DEFAULT_WEB_PORT
 }
@@ -148,7 +324,15 @@ pub struct Web {
 
 #[derive(Deserialize, Debug)]
 pub struct Team {
-	pub subnet: String,
+	// substituted for `{team}` (or `{team:x}`, in hex) in address templates
+	pub number: Option<u32>,
+	// overrides the top-level address template for this team
+	pub address: Option<String>,
+	// deprecated: `10.10.11.x` is read as the address template `10.10.11.{vm}`
+	pub subnet: Option<String>,
+	// explicit addresses for individual vms, keyed by vm alias
+	#[serde(default)]
+	pub overrides: HashMap<String, String>,
 	pub password: String,
 }
 
@@ -162,6 +346,8 @@ pub struct InjectMeta {
 pub struct ConfigInner {
 	pub round: String,
 	pub timing: Timing,
+	#[serde(default)]
+	pub concurrency: Concurrency,
 	pub scoring: Scoring,
 	pub web: Web,
 	#[serde(default)]
@@ -171,6 +357,10 @@ pub struct ConfigInner {
 	pub inject_meta: InjectMeta,
 	pub injects: Vec<Inject>,
 	pub teams: HashMap<String, Team>,
+	// where each team's vms are reached, e.g. `10.{team}.1.{vm}` or
+	// `{vm_name}.team{team}.local`. IPv6 templates take the numbers in hex, as
+	// `{team:x}` and `{vm:x}`
+	pub address: Option<String>,
 }
 
 #[derive(Clone)]
@@ -202,19 +392,235 @@ impl Config {
 	}
 }
 
+// RFC 1123 syntax, rejecting all-numeric names so malformed IPv4 addresses
+// aren't mistaken for hostnames
+fn is_hostname(address: &str) -> bool {
+	let labels: Vec<&str> = address.split('.').collect();
+	address.len() <= 253
+		&& labels.iter().all(|label| {
+			!label.is_empty()
+				&& label.len() <= 63
+				&& !label.starts_with('-')
+				&& !label.ends_with('-')
+				&& label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+		}) && !labels
+		.last()
+		.is_some_and(|label| label.chars().all(|c| c.is_ascii_digit()))
+}
+
 impl ConfigInner {
+	// the address (IP or hostname) of a team's instance of a vm
+	pub fn address(&self, team_alias: &str, vm_alias: &str) -> anyhow::Result<String> {
+		let Some(team) = self.teams.get(team_alias) else {
+			bail!("unknown team '{}'", team_alias);
+		};
+		let Some(vm) = self.vms.get(vm_alias) else {
+			bail!("unknown vm '{}'", vm_alias);
+		};
+		if let Some(address) = team.overrides.get(vm_alias) {
+			return Ok(address.to_owned());
+		}
+
+		let subnet = team
+			.subnet
+			.as_ref()
+			.map(|subnet| subnet.replace('x', "{vm}"));
+		let Some(template) = team
+			.address
+			.as_ref()
+			.or(subnet.as_ref())
+			.or(self.address.as_ref())
+		else {
+			bail!(
+				"team '{}' has no address for vm '{}' and no address template applies",
+				team_alias,
+				vm_alias
+			);
+		};
+		// IPv6 groups are hex, so a decimal vm ip of 10 would silently become 0x10
+		ensure!(
+			!template.contains(':') || !(template.contains("{vm}") || template.contains("{team}")),
+			"IPv6 address template '{}' must use {{vm:x}} and {{team:x}}, which substitute in hex",
+			template
+		);
+		let mut address = template
+			.replace("{vm}", &vm.ip.to_string())
+			.replace("{vm:x}", &format!("{:x}", vm.ip))
+			.replace("{vm_name}", vm_alias)
+			.replace("{team_name}", team_alias);
+		if address.contains("{team}") || address.contains("{team:x}") {
+			let Some(number) = team.number else {
+				bail!(
+					"address template '{}' uses the team number, but team '{}' has no number",
+					template,
+					team_alias
+				);
+			};
+			address = address
+				.replace("{team}", &number.to_string())
+				.replace("{team:x}", &format!("{:x}", number));
+		}
+		ensure!(
+			!address.contains(['{', '}']),
+			"unknown placeholder in address template '{}'",
+			template
+		);
+
+		Ok(address)
+	}
+
 	fn validate(&self) -> anyhow::Result<()> {
+		self.validate_timing()?;
 		self.validate_teams()?;
+		self.validate_web()?;
+		self.validate_vms()?;
 		self.validate_injects()
 	}
 
+	fn validate_timing(&self) -> anyhow::Result<()> {
+		ensure!(
+			self.timing.competition_length.duration() > chrono::Duration::zero(),
+			"competition length must be greater than zero"
+		);
+		ensure!(
+			self.timing.check_timeout > 0 && self.timing.check_attempts > 0,
+			"check timeout and attempts must be greater than zero"
+		);
+		ensure!(
+			self.slas.window > 0,
+			"SLA uptime window must be greater than zero"
+		);
+		ensure!(
+			self.concurrency.global > 0 && self.concurrency.per_team > 0,
+			"concurrency limits must be greater than zero"
+		);
+		Ok(())
+	}
+
+	fn validate_web(&self) -> anyhow::Result<()> {
+		ensure!(
+			!self.teams.contains_key(&self.web.admin_username),
+			"admin username '{}' conflicts with a team of the same name",
+			self.web.admin_username
+		);
+		Ok(())
+	}
+
 	fn validate_teams(&self) -> anyhow::Result<()> {
 		for (team_alias, team) in &self.teams {
-			let ip_str = team.subnet.replace('x', "1");
-			if ip_str.parse::<Ipv4Addr>().is_err() {
-				bail!("Invalid subnet for team '{}': {}", team_alias, team.subnet);
+			ensure!(
+				team.subnet.is_none() || team.address.is_none(),
+				"team '{}' sets both subnet and address; subnet was replaced by address",
+				team_alias
+			);
+			for vm_alias in team.overrides.keys() {
+				ensure!(
+					self.vms.contains_key(vm_alias),
+					"team '{}' overrides the address of unknown vm '{}'",
+					team_alias,
+					vm_alias
+				);
+			}
+
+			for vm_alias in self.vms.keys() {
+				let address = self.address(team_alias, vm_alias)?;
+				ensure!(
+					address.parse::<IpAddr>().is_ok() || is_hostname(&address),
+					"invalid address '{}' for vm '{}' of team '{}'",
+					address,
+					vm_alias,
+					team_alias
+				);
+			}
+		}
+		Ok(())
+	}
+
+	fn validate_vms(&self) -> anyhow::Result<()> {
+		for (vm_alias, vm) in &self.vms {
+			for (service_alias, service) in &vm.services {
+				ensure!(
+					service.timeout(&self.timing) > 0 && service.attempts(&self.timing) > 0,
+					"service '{}' on vm '{}' must have a timeout and attempts greater than zero",
+					service_alias,
+					vm_alias
+				);
+				ensure!(
+					service.max_consecutive_downs(&self.slas) > 0
+						&& service.min_uptime(&self.slas) <= 100,
+					"service '{}' on vm '{}' must have SLA max_consecutive_downs greater than zero and min_uptime of at most 100",
+					service_alias,
+					vm_alias
+				);
+
+				match &service.check {
+					Service::Icmp(icmp) => ensure!(
+						icmp.count > 0,
+						"icmp service '{}' on vm '{}' must have a count greater than zero",
+						service_alias,
+						vm_alias
+					),
+					Service::Http(http) => {
+						let https = http
+							.pages
+							.iter()
+							.filter(|page| matches!(page.scheme, check_types::HttpScheme::Https));
+						// not verifying would also accept certificates outside the bundle
+						ensure!(
+							https
+								.clone()
+								.all(|page| page.tls.verify || page.tls.ca_bundle.is_none()),
+							"service '{}' on vm '{}' sets a tls ca_bundle, which requires verify",
+							service_alias,
+							vm_alias
+						);
+						// every page of a run is fetched with the same client
+						ensure!(
+							https
+								.map(|page| {
+									(
+										page.tls.verify,
+										page.tls.ca_bundle.as_ref().map(|ca_bundle| &ca_bundle.path),
+									)
+								})
+								.all_equal(),
+							"https pages of service '{}' on vm '{}' must share the same tls verify and ca_bundle",
+							service_alias,
+							vm_alias
+						);
+					}
+					// `contains` and friends apply to the output of `command`
+					Service::Ssh(ssh) => ensure!(
+						ssh.command.is_some() || ssh.output.is_empty(),
+						"ssh service '{}' on vm '{}' has output expectations but no command",
+						service_alias,
+						vm_alias
+					),
+					_ => (),
+				}
+
+				let confirm_delivery = match &service.check {
+					Service::Pop3(check_types::Pop3 {
+						confirm_delivery, ..
+					})
+					| Service::Imap(check_types::Imap {
+						confirm_delivery, ..
+					}) => confirm_delivery,
+					_ => continue,
+				};
+				if let Some(smtp_alias) = confirm_delivery {
+					ensure!(
+						matches!(
+							vm.services.get(smtp_alias).map(|smtp| &smtp.check),
+							Some(Service::Smtp(_))
+						),
+						"service '{}' on vm '{}' confirms delivery via '{}', which is not an smtp service on the same vm",
+						service_alias, vm_alias, smtp_alias
+					);
+				}
 			}
 		}
+
 		Ok(())
 	}
 
@@ -238,3 +644,225 @@ impl ConfigInner {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// a minimal config with a single vm `web` (ip 3), followed by `rest`
+	fn config(rest: &str) -> anyhow::Result<Config> {
+		config_with_services("{}", rest)
+	}
+
+	// as `config`, with `services` as the services of `web`
+	fn config_with_services(services: &str, rest: &str) -> anyhow::Result<Config> {
+		Config::from_str(&format!(
+			"
+round: test
+timing: {{}}
+scoring: {{}}
+web:
+  admin_username: admin
+  admin_password: admin
+vms:
+  web:
+    ip: 3
+    services: {}
+inject_meta:
+  source_dir: injects
+  assets_dir: assets
+injects: []
+{}",
+			services, rest
+		))
+	}
+
+	// `config_with_services` for a single service `svc`, with one team
+	fn service(check: &str) -> anyhow::Result<Config> {
+		config_with_services(
+			&format!("{{svc: {}}}", check),
+			"
+address: 10.1.1.{vm}
+teams:
+  red:
+    password: x
+",
+		)
+	}
+
+	#[test]
+	fn icmp_needs_a_count() {
+		assert!(service("{type: icmp}").is_ok());
+		assert!(service("{type: icmp, count: 0}").is_err());
+	}
+
+	#[test]
+	fn ssh_expectations_need_a_command() {
+		let auth = "auth: {method: password, user: u, password: p}";
+		assert!(service(&format!(
+			"{{type: ssh, {}, command: id, contains: u}}",
+			auth
+		))
+		.is_ok());
+		assert!(service(&format!("{{type: ssh, {}}}", auth)).is_ok());
+		assert!(service(&format!("{{type: ssh, {}, contains: u}}", auth)).is_err());
+		assert!(service(&format!("{{type: ssh, {}, equals: u}}", auth)).is_err());
+	}
+
+	#[test]
+	fn ca_bundles_need_verify() -> anyhow::Result<()> {
+		use openssl::{asn1::Asn1Time, hash::MessageDigest, pkey::PKey, rsa::Rsa, x509::X509};
+
+		let key = PKey::from_rsa(Rsa::generate(2048)?)?;
+		let mut cert = X509::builder()?;
+		cert.set_pubkey(&key)?;
+		cert.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+		cert.set_not_after(&*Asn1Time::days_from_now(1)?)?;
+		cert.sign(&key, MessageDigest::sha256())?;
+		let path = std::env::temp_dir().join(format!("cobalt-ca-{}.pem", std::process::id()));
+		std::fs::write(&path, cert.build().to_pem()?)?;
+
+		let page = |tls: &str| {
+			service(&format!(
+				"{{type: http, pages: [{{path: /, scheme: https, tls: {}}}]}}",
+				tls
+			))
+		};
+		let verified = page(&format!("{{ca_bundle: {}}}", path.display()));
+		let unverified = page(&format!("{{verify: false, ca_bundle: {}}}", path.display()));
+		let missing = page("{ca_bundle: /nonexistent.pem}");
+		std::fs::remove_file(&path)?;
+
+		assert!(verified.is_ok());
+		assert!(unverified.is_err());
+		assert!(missing.is_err());
+		Ok(())
+	}
+
+	#[test]
+	fn address_substitutes_placeholders() {
+		let cfg = config(
+			"
+address: 10.{team}.1.{vm}
+teams:
+  red:
+    number: 12
+    password: x
+  blue:
+    address: '{vm_name}.{team_name}.local'
+    password: x
+",
+		)
+		.unwrap();
+		assert_eq!(cfg.address("red", "web").unwrap(), "10.12.1.3");
+		assert_eq!(cfg.address("blue", "web").unwrap(), "web.blue.local");
+	}
+
+	#[test]
+	fn address_prefers_overrides_and_reads_subnets() {
+		let cfg = config(
+			"
+address: 10.{team}.1.{vm}
+teams:
+  red:
+    number: 12
+    password: x
+    overrides:
+      web: fd00::1
+  blue:
+    subnet: 10.10.11.x
+    password: x
+",
+		)
+		.unwrap();
+		assert_eq!(cfg.address("red", "web").unwrap(), "fd00::1");
+		assert_eq!(cfg.address("blue", "web").unwrap(), "10.10.11.3");
+	}
+
+	#[test]
+	fn address_substitutes_hex_in_ipv6() {
+		let cfg = config(
+			"
+address: fd00:{team:x}::{vm:x}
+teams:
+  red:
+    number: 12
+    password: x
+",
+		)
+		.unwrap();
+		assert_eq!(cfg.address("red", "web").unwrap(), "fd00:c::3");
+
+		for address in ["fd00:{team}::{vm:x}", "fd00:{team:x}::{vm}"] {
+			let cfg = config(&format!(
+				"
+address: '{}'
+teams:
+  red:
+    number: 12
+    password: x
+",
+				address
+			));
+			assert!(cfg.is_err(), "'{}' was accepted", address);
+		}
+	}
+
+	#[test]
+	fn address_rejects_bad_placeholders() {
+		let missing_number = config(
+			"
+address: 10.{team}.1.{vm}
+teams:
+  red:
+    password: x
+",
+		);
+		assert!(missing_number.is_err());
+
+		let unknown = config(
+			"
+address: 10.1.1.{host}
+teams:
+  red:
+    password: x
+",
+		);
+		assert!(unknown.is_err());
+
+		let no_template = config(
+			"
+teams:
+  red:
+    password: x
+",
+		);
+		assert!(no_template.is_err());
+	}
+
+	#[test]
+	fn address_rejects_invalid_addresses() {
+		for address in ["10.1.1.300", "fd00:::{vm}", "-web.local", "web..local"] {
+			let cfg = config(&format!(
+				"
+address: '{}'
+teams:
+  red:
+    password: x
+",
+				address
+			));
+			assert!(cfg.is_err(), "'{}' was accepted", address);
+		}
+	}
+
+	#[test]
+	fn hostnames() {
+		assert!(is_hostname("web"));
+		assert!(is_hostname("web-1.team12.local"));
+		assert!(!is_hostname("10.1.1.300"));
+		assert!(!is_hostname("-web.local"));
+		assert!(!is_hostname("web_1.local"));
+		assert!(!is_hostname(&"a".repeat(64)));
+	}
+}
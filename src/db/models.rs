@@ -1,42 +1,37 @@
-use chrono::{serde::ts_seconds, DateTime, Utc};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, FromRow)]
-pub struct ServiceCheck {
-	pub team: String,
-	pub service: String,
-	pub up: bool,
-	pub short_error: Option<String>,
-	pub long_error: Option<String>,
-	#[serde(with = "ts_seconds")]
-	pub time: DateTime<Utc>,
-}
-
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct ServiceGatheredInfo {
 	pub up: bool,
 	pub incurred_sla: bool,
+	// replaces the configured up/down points when set by the check
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub points: Option<i32>,
+	// replaces `scoring.sla` when the service overrides it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub sla_penalty: Option<i32>,
 }
 
 pub type ServiceMap<'s> = HashMap<&'s str, HashMap<&'s str, ServiceGatheredInfo>>;
 
-#[derive(Serialize, Deserialize, FromRow)]
-pub struct TeamSnapshot<'s> {
-	pub team: String,
-	pub points: i32,
-	// { [vm: string]: { [service: string]: boolean } }
-	#[serde(borrow)]
-	pub services: ServiceMap<'s>,
-	#[serde(with = "ts_seconds")]
+#[derive(FromRow)]
+pub struct EngineStateRecord {
+	pub state: String,
+	pub time_started: Option<DateTime<Utc>>,
+	pub time_remaining_ms: Option<i64>,
 	pub time: DateTime<Utc>,
+	pub last_seen: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, FromRow)]
-pub struct SlaViolation {
+#[derive(FromRow)]
+pub struct PasswordChange {
 	pub team: String,
+	pub vm: String,
 	pub service: String,
-	#[serde(with = "ts_seconds")]
+	pub username: String,
+	pub password: String,
 	pub time: DateTime<Utc>,
 }
@@ -2,12 +2,36 @@ use crate::{
 	checks::{errors::get_check_result_errors, CheckResult},
 	config::Scoring,
 	db::models::ServiceMap,
+	state::EngineState,
 };
 use chrono::{DateTime, Utc};
 use sqlx::{types::Json, PgExecutor};
 
+// rounds are only inserted once finished, in the same transaction as their
+// results, so numbers are never skipped by a round that failed to record
+pub async fn insert_round(
+	conn: impl PgExecutor<'_>,
+	time_started: DateTime<Utc>,
+	time_finished: DateTime<Utc>,
+) -> anyhow::Result<i32> {
+	let round = sqlx::query_scalar!(
+		r#"
+			INSERT INTO rounds(number, time_started, time_finished)
+			SELECT COALESCE(MAX(number), 0) + 1, $1, $2 FROM rounds
+			RETURNING number;
+		"#,
+		time_started,
+		time_finished
+	)
+	.fetch_one(conn)
+	.await?;
+
+	Ok(round)
+}
+
 pub async fn report_sla_violation(
 	conn: impl PgExecutor<'_>,
+	round: i32,
 	team: &str,
 	vm: &str,
 	service: &str,
@@ -15,9 +39,10 @@ pub async fn report_sla_violation(
 ) -> anyhow::Result<()> {
 	sqlx::query!(
 		r#"
-			INSERT INTO sla_violations(team, vm, service, time)
-			VALUES ($1, $2, $3, $4);
+			INSERT INTO sla_violations(round, team, vm, service, time)
+			VALUES ($1, $2, $3, $4, $5);
 		"#,
+		round,
 		team,
 		vm,
 		service,
@@ -29,28 +54,34 @@ pub async fn report_sla_violation(
 	Ok(())
 }
 
+// one argument per column of service_checks
+#[allow(clippy::too_many_arguments)]
 pub async fn record_service(
 	conn: impl PgExecutor<'_>,
+	round: i32,
 	team: &str,
 	vm: &str,
 	service: &str,
 	time: DateTime<Utc>,
 	status: &CheckResult,
+	attempts: u32,
 ) -> anyhow::Result<()> {
 	let (short, long) = get_check_result_errors(status);
 
 	sqlx::query!(
 		r#"
-			INSERT INTO service_checks(team, vm, service, up, short_error, long_error, time)
-			VALUES ($1, $2, $3, $4, $5, $6, $7);
+			INSERT INTO service_checks(round, team, vm, service, up, short_error, long_error, time, attempts)
+			VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9);
 		"#,
+		round,
 		team,
 		vm,
 		service,
 		status.is_ok(),
 		short,
 		long,
-		time
+		time,
+		attempts as i32
 	)
 	.execute(conn)
 	.await?;
@@ -60,6 +91,7 @@ pub async fn record_service(
 
 pub async fn snapshot_team<'s>(
 	conn: impl PgExecutor<'_>,
+	round: i32,
 	team: &str,
 	service_map: ServiceMap<'s>,
 	scoring_info: Scoring,
@@ -69,11 +101,11 @@ pub async fn snapshot_team<'s>(
 	for vm in service_map.values() {
 		for service in vm.values() {
 			if service.up {
-				point_differential += scoring_info.service_up;
+				point_differential += service.points.unwrap_or(scoring_info.service_up);
 			} else {
-				point_differential += scoring_info.service_down;
+				point_differential += service.points.unwrap_or(scoring_info.service_down);
 				if service.incurred_sla {
-					point_differential += scoring_info.sla;
+					point_differential += service.sla_penalty.unwrap_or(scoring_info.sla);
 				}
 			}
 		}
@@ -81,9 +113,15 @@ pub async fn snapshot_team<'s>(
 
 	sqlx::query!(
 		r#"
-		INSERT INTO team_snapshots(team, points, services, time)
-		VALUES ($1, $2, $3, $4);
+		INSERT INTO team_snapshots(round, team, points, total_points, services, time)
+		SELECT $1, $2, $3, COALESCE((
+			SELECT total_points FROM team_snapshots
+			WHERE team = $2
+			ORDER BY time DESC, id DESC
+			LIMIT 1
+		), 0) + $3, $4, $5;
 	"#,
+		round,
 		team,
 		point_differential,
 		Json(service_map) as _,
@@ -94,3 +132,78 @@ pub async fn snapshot_team<'s>(
 
 	Ok(())
 }
+
+pub async fn record_engine_state(
+	conn: impl PgExecutor<'_>,
+	engine_state: EngineState,
+	time: DateTime<Utc>,
+) -> anyhow::Result<()> {
+	let (state, time_started, time_remaining) = match engine_state {
+		EngineState::Uninitialized => ("uninitialized", None, None),
+		EngineState::Started {
+			time_started,
+			time_remaining_at_start,
+		} => ("started", Some(time_started), Some(time_remaining_at_start)),
+		EngineState::Stopped { time_remaining } => ("stopped", None, Some(time_remaining)),
+		EngineState::Finished => ("finished", None, None),
+	};
+
+	sqlx::query(
+		r#"
+			INSERT INTO engine_states(state, time_started, time_remaining_ms, time, last_seen)
+			VALUES ($1, $2, $3, $4, $4);
+		"#,
+	)
+	.bind(state)
+	.bind(time_started)
+	.bind(time_remaining.map(|remaining| remaining.num_milliseconds()))
+	.bind(time)
+	.execute(conn)
+	.await?;
+
+	Ok(())
+}
+
+pub async fn engine_heartbeat(
+	conn: impl PgExecutor<'_>,
+	time: DateTime<Utc>,
+) -> anyhow::Result<()> {
+	sqlx::query(
+		r#"
+			UPDATE engine_states SET last_seen = $1
+			WHERE id = (SELECT MAX(id) FROM engine_states);
+		"#,
+	)
+	.bind(time)
+	.execute(conn)
+	.await?;
+
+	Ok(())
+}
+
+pub async fn record_password_change(
+	conn: impl PgExecutor<'_>,
+	team: &str,
+	vm: &str,
+	service: &str,
+	username: &str,
+	password: &str,
+	time: DateTime<Utc>,
+) -> anyhow::Result<()> {
+	sqlx::query(
+		r#"
+			INSERT INTO password_changes(team, vm, service, username, password, time)
+			VALUES ($1, $2, $3, $4, $5, $6);
+		"#,
+	)
+	.bind(team)
+	.bind(vm)
+	.bind(service)
+	.bind(username)
+	.bind(password)
+	.bind(time)
+	.execute(conn)
+	.await?;
+
+	Ok(())
+}
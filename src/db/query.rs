@@ -1,21 +1,16 @@
-use crate::db::models::ServiceGatheredInfo;
+use crate::db::models::{EngineStateRecord, PasswordChange, ServiceGatheredInfo};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, types::Json, PgExecutor};
 use std::collections::HashMap;
 
-#[derive(FromRow)]
-pub struct ServiceStatus {
-	pub up: bool,
-}
-
-pub async fn check_sla_violation(
+// downs since the service was last up
+pub async fn consecutive_downs(
 	conn: impl PgExecutor<'_>,
 	team: &str,
 	vm: &str,
 	service: &str,
-	limit: u32,
-) -> anyhow::Result<bool> {
+) -> anyhow::Result<i64> {
 	let count: i64 = sqlx::query_scalar(
 		r#"
 			WITH LatestUpTrue AS (
@@ -36,15 +31,65 @@ pub async fn check_sla_violation(
 	.fetch_one(conn)
 	.await?;
 
-	Ok(count > 0 && count % (limit as i64) == 0)
+	Ok(count)
+}
+
+#[derive(FromRow)]
+pub struct UptimeWindow {
+	pub checks: i64,
+	pub ups: i64,
+	// whether an SLA violation was already reported within the window
+	pub penalized: bool,
+}
+
+// the service's last `window` checks
+pub async fn uptime_window(
+	conn: impl PgExecutor<'_>,
+	team: &str,
+	vm: &str,
+	service: &str,
+	window: u32,
+) -> anyhow::Result<UptimeWindow> {
+	let uptime = sqlx::query_as::<_, UptimeWindow>(
+		r#"
+			WITH Recent AS (
+				SELECT up, time
+				FROM service_checks
+				WHERE team = $1 AND vm = $2 AND service = $3
+				ORDER BY time DESC
+				LIMIT $4
+			)
+			SELECT
+				COUNT(*) AS checks,
+				COUNT(*) FILTER (WHERE up) AS ups,
+				EXISTS (
+					SELECT 1
+					FROM sla_violations
+					WHERE
+						team = $1 AND vm = $2 AND service = $3 AND
+						time >= (SELECT MIN(time) FROM Recent)
+				) AS penalized
+			FROM Recent
+	"#,
+	)
+	.bind(team)
+	.bind(vm)
+	.bind(service)
+	.bind(window as i64)
+	.fetch_one(conn)
+	.await?;
+
+	Ok(uptime)
 }
 
 pub type OwnedServiceMap = HashMap<String, HashMap<String, ServiceGatheredInfo>>;
 
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct LatestTeamSnapshot {
+	pub round: Option<i32>,
 	pub team: String,
 	pub points: i32,
+	pub total_points: i32,
 	// { [vm: string]: { [service: string]: boolean } }
 	pub services: Json<OwnedServiceMap>,
 	pub time: DateTime<Utc>,
@@ -55,9 +100,9 @@ pub async fn latest_service_statuses(
 ) -> anyhow::Result<Vec<LatestTeamSnapshot>> {
 	let teams = sqlx::query_as::<_, LatestTeamSnapshot>(
 		r#"
-		SELECT DISTINCT ON (team) team, points, services, time
+		SELECT DISTINCT ON (team) round, team, points, total_points, services, time
 		FROM team_snapshots
-		ORDER BY team, time DESC;
+		ORDER BY team, time DESC, id DESC;
 	"#,
 	)
 	.fetch_all(conn)
@@ -69,17 +114,70 @@ pub async fn latest_service_statuses(
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct TeamProgression {
 	pub team: String,
-	pub points: i32,
+	pub total_points: i32,
 	pub time: DateTime<Utc>,
 }
 
 pub async fn team_progressions(conn: impl PgExecutor<'_>) -> anyhow::Result<Vec<TeamProgression>> {
 	let progression = sqlx::query_as!(
 		TeamProgression,
-		"SELECT team, points, time FROM team_snapshots"
+		"SELECT team, total_points, time FROM team_snapshots ORDER BY time"
 	)
 	.fetch_all(conn)
 	.await?;
 
 	Ok(progression)
 }
+
+pub async fn latest_engine_state(
+	conn: impl PgExecutor<'_>,
+) -> anyhow::Result<Option<EngineStateRecord>> {
+	let state = sqlx::query_as::<_, EngineStateRecord>(
+		r#"
+		SELECT state, time_started, time_remaining_ms, time, last_seen
+		FROM engine_states
+		ORDER BY id DESC
+		LIMIT 1;
+	"#,
+	)
+	.fetch_optional(conn)
+	.await?;
+
+	Ok(state)
+}
+
+// the most recent change for each team, service and user
+pub async fn latest_password_changes(
+	conn: impl PgExecutor<'_>,
+) -> anyhow::Result<Vec<PasswordChange>> {
+	let changes = sqlx::query_as::<_, PasswordChange>(
+		r#"
+		SELECT DISTINCT ON (team, vm, service, username) team, vm, service, username, password, time
+		FROM password_changes
+		ORDER BY team, vm, service, username, time DESC;
+	"#,
+	)
+	.fetch_all(conn)
+	.await?;
+
+	Ok(changes)
+}
+
+pub async fn team_password_changes(
+	conn: impl PgExecutor<'_>,
+	team: &str,
+) -> anyhow::Result<Vec<PasswordChange>> {
+	let changes = sqlx::query_as::<_, PasswordChange>(
+		r#"
+		SELECT team, vm, service, username, password, time
+		FROM password_changes
+		WHERE team = $1
+		ORDER BY time DESC;
+	"#,
+	)
+	.bind(team)
+	.fetch_all(conn)
+	.await?;
+
+	Ok(changes)
+}
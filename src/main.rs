@@ -10,7 +10,7 @@ mod web;
 
 use crate::{config::Config, state::Timer};
 use dotenvy::dotenv;
-use log::{debug, LevelFilter};
+use log::{debug, info, LevelFilter};
 use std::fs;
 
 #[tokio::main]
@@ -21,9 +21,15 @@ async fn main() -> anyhow::Result<()> {
 	let raw = fs::read_to_string("cobalt.yml")?;
 	let cfg = Config::from_str(&raw)?;
 	debug!("Parsed configuration: {:#?}", cfg);
+	checks::preflight(&cfg)?;
 	let pool = db::establish_pg_conn().await?;
-	let timer = Timer::default();
+	let timer = Timer::restore(cfg.timing.competition_length.duration(), &pool).await?;
+	info!(
+		"Restored engine state: {:?}",
+		timer.read().await.engine_state
+	);
 
+	tokio::spawn(state::heartbeat(timer.clone(), pool.clone()));
 	tokio::spawn(score::run(cfg.clone(), timer.clone(), pool.clone()));
 	web::run(cfg, timer, pool).await
 }
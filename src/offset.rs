@@ -4,9 +4,21 @@ use std::fmt;
 
 // The following has been generated with the help of ChatGPT
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Offset(Duration);
 
+impl Offset {
+	pub fn duration(&self) -> Duration {
+		self.0
+	}
+}
+
+impl From<Duration> for Offset {
+	fn from(value: Duration) -> Self {
+		Self(value)
+	}
+}
+
 impl std::fmt::Display for Offset {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let total_seconds = self.0.num_seconds();
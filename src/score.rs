@@ -1,104 +1,538 @@
 use crate::{
-	checks::{errors::check_error, Check},
-	config::Config,
-	db::{self, models::ServiceGatheredInfo},
+	checks::{errors::check_error, Check, CheckResult, Passwords, Target},
+	config::{Config, SlaPolicy},
+	db::{
+		self,
+		models::{ServiceGatheredInfo, ServiceMap},
+		query::UptimeWindow,
+	},
 	shuffle::ShuffleIterExt,
 	state::{EngineState, Timer},
 };
-use chrono::Utc;
-use log::{debug, info};
-use sqlx::PgPool;
-use std::{collections::HashMap, net::Ipv4Addr, str::FromStr, time::Duration};
-use tokio::time::timeout;
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use sqlx::{PgConnection, PgPool};
+use std::{collections::HashMap, net::IpAddr, sync::Arc, time::Duration};
+use tokio::{sync::Semaphore, task::JoinSet, time::timeout};
 
-async fn score(cfg: Config, pool: PgPool) -> anyhow::Result<()> {
-	for (team_alias, team) in cfg.teams.iter().shuffle() {
-		let mut team_snapshot = HashMap::new();
-		for (vm_alias, vm) in cfg.vms.iter().shuffle() {
-			let mut vm_snapshot = HashMap::new();
-			for (service_alias, service) in vm.services.iter().shuffle() {
-				debug!(
-					"Commencing scoring check for team='{}', vm='{}', service='{}'",
-					team_alias, vm_alias, service_alias
-				);
+// attempts at writing a round's results before it is dropped
+const ROUND_WRITE_ATTEMPTS: u32 = 3;
+// delay before the first retry, doubled after each subsequent failure
+const ROUND_WRITE_BACKOFF: Duration = Duration::from_secs(1);
+
+// hostnames are looked up on every check, so DNS changes apply immediately
+async fn resolve(host: &str) -> CheckResult<IpAddr> {
+	if let Ok(ip) = host.parse() {
+		return Ok(ip);
+	}
+
+	let addr = tokio::net::lookup_host((host, 0))
+		.await
+		.map_err(|e| {
+			check_error!(
+				"Resolution failed",
+				format!("Failed to resolve '{}': {}", host, e)
+			)
+		})?
+		.next();
+	match addr {
+		Some(addr) => Ok(addr.ip()),
+		None => Err(check_error!(
+			"Resolution failed",
+			format!("'{}' has no addresses", host)
+		)),
+	}
+}
+
+struct ServiceResult {
+	vm_alias: String,
+	service_alias: String,
+	time: DateTime<Utc>,
+	res: CheckResult,
+	points: Option<i32>,
+	attempts: u32,
+}
+
+impl ServiceResult {
+	fn failed(vm_alias: String, service_alias: String, long: &'static str) -> Self {
+		Self {
+			vm_alias,
+			service_alias,
+			time: Utc::now(),
+			res: Err(check_error!("Internal server error", long)),
+			points: None,
+			attempts: 1,
+		}
+	}
+}
+
+async fn attempt_check(
+	cfg: &Config,
+	team_alias: &str,
+	vm_alias: &str,
+	service_alias: &str,
+	passwords: &Passwords,
+) -> (CheckResult, Option<i32>) {
+	let vm = &cfg.vms[vm_alias];
+	let service = &vm.services[service_alias];
+	// pre-validated
+	let host = cfg.address(team_alias, vm_alias).unwrap();
+
+	let timeout_secs = service.timeout(&cfg.timing);
+	timeout(Duration::from_secs(timeout_secs as u64), async {
+		let ip = match resolve(&host).await {
+			Ok(ip) => ip,
+			Err(e) => return (Err(e), None),
+		};
+		let target = Target {
+			ip,
+			host: &host,
+			team: team_alias,
+			vm_alias,
+			vm,
+			service: service_alias,
+			passwords,
+		};
+		service.check.score_with_points(&target).await
+	})
+	.await
+	.unwrap_or_else(|_| {
+		(
+			Err(check_error!(
+				"Timed out",
+				format!("Timed out after {} seconds", timeout_secs)
+			)),
+			None,
+		)
+	})
+}
+
+async fn check_service(
+	cfg: Config,
+	team_alias: String,
+	vm_alias: String,
+	service_alias: String,
+	passwords: Arc<Passwords>,
+	team_permits: Arc<Semaphore>,
+	global_permits: Arc<Semaphore>,
+) -> ServiceResult {
+	let service = &cfg.vms[&vm_alias].services[&service_alias];
+	let max_attempts = service.attempts(&cfg.timing);
+	let retry_delay = Duration::from_secs(service.retry_delay(&cfg.timing) as u64);
+
+	let time = Utc::now();
+	let mut attempts = 0;
+	let (res, points) = loop {
+		attempts += 1;
+		let (res, points) = {
+			// permits aren't held between attempts
+			let _team_permit = team_permits.acquire().await;
+			let _global_permit = global_permits.acquire().await;
+
+			debug!(
+				"Commencing scoring check for team='{}', vm='{}', service='{}' (attempt {}/{})",
+				team_alias, vm_alias, service_alias, attempts, max_attempts
+			);
+			attempt_check(&cfg, &team_alias, &vm_alias, &service_alias, &passwords).await
+		};
+		if res.is_ok() || attempts >= max_attempts {
+			break (res, points);
+		}
+
+		debug!(
+			"Scoring check for team='{}', vm='{}', service='{}' failed, retrying in {:?}: {:?}",
+			team_alias, vm_alias, service_alias, retry_delay, res
+		);
+		tokio::time::sleep(retry_delay).await;
+	};
+
+	info!(
+		"Result of scoring check for team='{}', vm='{}', service='{}' after {} attempt(s): {:?}",
+		team_alias, vm_alias, service_alias, attempts, res
+	);
+
+	ServiceResult {
+		vm_alias,
+		service_alias,
+		time,
+		res,
+		points,
+		attempts,
+	}
+}
 
-				// pre-validated
-				let ip = Ipv4Addr::from_str(&team.subnet.replace('x', &vm.ip.to_string())).unwrap();
-				let time = Utc::now();
-				let res = timeout(
-					Duration::from_secs(cfg.timing.check_timeout as u64),
-					service.score(ip, vm),
+struct TeamResults {
+	team_alias: String,
+	time: DateTime<Utc>,
+	services: Vec<ServiceResult>,
+}
+
+async fn score_team(
+	cfg: Config,
+	team_alias: String,
+	passwords: Passwords,
+	global_permits: Arc<Semaphore>,
+) -> TeamResults {
+	let passwords = Arc::new(passwords);
+	let team_permits = Arc::new(Semaphore::new(cfg.concurrency.per_team));
+	let mut checks = JoinSet::new();
+	let mut spawned = Vec::new();
+	for (vm_alias, vm) in cfg.vms.iter().shuffle() {
+		for service_alias in vm.services.keys().shuffle() {
+			spawned.push((vm_alias.clone(), service_alias.clone()));
+			checks.spawn(check_service(
+				cfg.clone(),
+				team_alias.clone(),
+				vm_alias.clone(),
+				service_alias.clone(),
+				passwords.clone(),
+				team_permits.clone(),
+				global_permits.clone(),
+			));
+		}
+	}
+
+	let mut services: Vec<ServiceResult> = Vec::new();
+	while let Some(result) = checks.join_next().await {
+		match result {
+			Ok(service) => services.push(service),
+			Err(e) => error!("Scoring check for team='{}' panicked: {}", team_alias, e),
+		}
+	}
+
+	// a panicked check doesn't say which service it was for, so any service
+	// left without a result is scored down
+	for (vm_alias, service_alias) in spawned {
+		let checked = services
+			.iter()
+			.any(|service| service.vm_alias == vm_alias && service.service_alias == service_alias);
+		if !checked {
+			services.push(ServiceResult::failed(
+				vm_alias,
+				service_alias,
+				"Scoring check panicked",
+			));
+		}
+	}
+
+	TeamResults {
+		team_alias,
+		time: Utc::now(),
+		services,
+	}
+}
+
+// scores every service of a team whose scoring task panicked as down
+fn failed_team(cfg: &Config, team_alias: String) -> TeamResults {
+	let services = cfg
+		.vms
+		.iter()
+		.flat_map(|(vm_alias, vm)| {
+			vm.services.keys().map(|service_alias| {
+				ServiceResult::failed(
+					vm_alias.clone(),
+					service_alias.clone(),
+					"Scoring team panicked",
 				)
-				.await
-				.map_err(|_| {
-					check_error!(
-						"Timed out",
-						format!("Timed out after {} seconds", cfg.timing.check_timeout)
-					)
-				})
-				.and_then(|ok| ok);
+			})
+		})
+		.collect();
+
+	TeamResults {
+		team_alias,
+		time: Utc::now(),
+		services,
+	}
+}
+
+// whether a service down `downs` times in a row incurs an SLA violation under
+// a repeating or one-shot policy
+fn downs_incur_sla(policy: SlaPolicy, downs: i64, limit: i64) -> bool {
+	match policy {
+		SlaPolicy::OneShot => downs == limit,
+		_ => downs > 0 && downs % limit == 0,
+	}
+}
+
+// whether a service's uptime over its last `size` checks incurs an SLA
+// violation; judged only once the window is full
+fn window_incurs_sla(window: &UptimeWindow, size: u32, min_uptime: u32) -> bool {
+	window.checks >= size as i64
+		&& window.ups * 100 < min_uptime as i64 * window.checks
+		&& !window.penalized
+}
+
+// whether the check just recorded for the service incurs an SLA violation
+async fn check_sla(
+	conn: &mut PgConnection,
+	cfg: &Config,
+	team_alias: &str,
+	vm_alias: &str,
+	service_alias: &str,
+) -> anyhow::Result<bool> {
+	let service = &cfg.vms[vm_alias].services[service_alias];
+	match cfg.slas.policy {
+		SlaPolicy::Repeating | SlaPolicy::OneShot => {
+			let limit = service.max_consecutive_downs(&cfg.slas) as i64;
+			let downs =
+				db::query::consecutive_downs(conn, team_alias, vm_alias, service_alias).await?;
+			Ok(downs_incur_sla(cfg.slas.policy, downs, limit))
+		}
+		SlaPolicy::Uptime => {
+			let window = db::query::uptime_window(
+				conn,
+				team_alias,
+				vm_alias,
+				service_alias,
+				cfg.slas.window,
+			)
+			.await?;
+			Ok(window_incurs_sla(
+				&window,
+				cfg.slas.window,
+				service.min_uptime(&cfg.slas),
+			))
+		}
+	}
+}
+
+async fn record_round(
+	cfg: &Config,
+	pool: &PgPool,
+	time_started: DateTime<Utc>,
+	results: &[TeamResults],
+) -> anyhow::Result<i32> {
+	let mut tx = pool.begin().await?;
+	let round = db::mutation::insert_round(&mut *tx, time_started, Utc::now()).await?;
+
+	for TeamResults {
+		team_alias,
+		time,
+		services,
+	} in results
+	{
+		let mut team_snapshot: ServiceMap = HashMap::new();
+		for ServiceResult {
+			vm_alias,
+			service_alias,
+			time,
+			res,
+			points,
+			attempts,
+		} in services
+		{
+			db::mutation::record_service(
+				&mut *tx,
+				round,
+				team_alias,
+				vm_alias,
+				service_alias,
+				*time,
+				res,
+				*attempts,
+			)
+			.await?;
 
+			let service = &cfg.vms[vm_alias].services[service_alias];
+			// SLAs only apply to downs, which is also when they're scored
+			let incurred_sla = cfg.slas.enable
+				&& res.is_err()
+				&& check_sla(&mut tx, cfg, team_alias, vm_alias, service_alias).await?;
+			if incurred_sla {
 				info!(
-					"Result of scoring check for team='{}', vm='{}', service='{}': {:?}",
-					team_alias, vm_alias, service_alias, res
+					"SLA incurred for team='{}', vm='{}', service='{}'",
+					team_alias, vm_alias, service_alias
 				);
-
-				db::mutation::record_service(
-					&pool,
+				db::mutation::report_sla_violation(
+					&mut *tx,
+					round,
 					team_alias,
 					vm_alias,
 					service_alias,
-					time,
-					&res,
+					*time,
 				)
 				.await?;
+			}
 
-				let incurred_sla = db::query::check_sla_violation(
-					&pool,
-					team_alias,
-					vm_alias,
-					service_alias,
-					cfg.slas.max_consecutive_downs,
-				)
-				.await?;
-				if incurred_sla {
-					info!(
-						"SLA incurred for team='{}', vm='{}', service='{}'",
-						team_alias, vm_alias, service_alias
-					);
-					db::mutation::report_sla_violation(
-						&pool,
-						team_alias,
-						vm_alias,
-						service_alias,
-						time,
-					)
-					.await?;
-				}
-
-				vm_snapshot.insert(
-					service_alias.as_str(),
-					ServiceGatheredInfo {
-						up: res.is_ok(),
-						incurred_sla,
-					},
+			team_snapshot.entry(vm_alias.as_str()).or_default().insert(
+				service_alias.as_str(),
+				ServiceGatheredInfo {
+					up: res.is_ok(),
+					incurred_sla,
+					points: *points,
+					sla_penalty: service.sla.penalty.filter(|_| incurred_sla),
+				},
+			);
+		}
+
+		db::mutation::snapshot_team(
+			&mut *tx,
+			round,
+			team_alias,
+			team_snapshot,
+			cfg.scoring,
+			*time,
+		)
+		.await?;
+	}
+
+	tx.commit().await?;
+
+	Ok(round)
+}
+
+async fn score(cfg: Config, pool: PgPool) -> anyhow::Result<()> {
+	let time_started = Utc::now();
+	info!("Commencing scoring round");
+
+	// credentials teams have changed through PCRs, fixed for the whole round
+	let mut passwords: HashMap<String, Passwords> = HashMap::new();
+	for change in db::query::latest_password_changes(&pool).await? {
+		passwords.entry(change.team).or_default().insert(
+			(change.vm, change.service, change.username),
+			change.password,
+		);
+	}
+
+	let global_permits = Arc::new(Semaphore::new(cfg.concurrency.global));
+	let mut teams = JoinSet::new();
+	for team_alias in cfg.teams.keys().shuffle() {
+		teams.spawn(score_team(
+			cfg.clone(),
+			team_alias.clone(),
+			passwords.remove(team_alias).unwrap_or_default(),
+			global_permits.clone(),
+		));
+	}
+
+	let mut results: Vec<TeamResults> = Vec::new();
+	while let Some(res) = teams.join_next().await {
+		match res {
+			Ok(team) => results.push(team),
+			Err(e) => error!("Scoring team panicked: {}", e),
+		}
+	}
+
+	// as with checks, a panicked team can't be identified from its error
+	for team_alias in cfg.teams.keys() {
+		if !results.iter().any(|team| &team.team_alias == team_alias) {
+			results.push(failed_team(&cfg, team_alias.clone()));
+		}
+	}
+
+	// the whole round, including its row in `rounds`, is written in one
+	// transaction, so a failed attempt leaves nothing behind and can simply be
+	// replayed
+	let mut backoff = ROUND_WRITE_BACKOFF;
+	for attempt in 1..=ROUND_WRITE_ATTEMPTS {
+		match record_round(&cfg, &pool, time_started, &results).await {
+			Ok(round) => {
+				info!("Recorded scoring round {}", round);
+				return Ok(());
+			}
+			Err(e) if attempt < ROUND_WRITE_ATTEMPTS => {
+				warn!(
+					"Failed to record round (attempt {}/{}): {:#}",
+					attempt, ROUND_WRITE_ATTEMPTS, e
 				);
+				tokio::time::sleep(backoff).await;
+				backoff *= 2;
 			}
-			team_snapshot.insert(vm_alias.as_str(), vm_snapshot);
+			Err(e) => return Err(e.context("failed to record round")),
 		}
-
-		let time = Utc::now();
-		db::mutation::snapshot_team(&pool, team_alias, team_snapshot, cfg.scoring, time).await?;
 	}
 
 	Ok(())
 }
 
 pub async fn run(cfg: Config, timer: Timer, pool: PgPool) -> anyhow::Result<()> {
+	let mut state_rx = timer.subscribe();
 	loop {
-		if matches!(timer.read().await.engine_state, EngineState::Started { .. }) {
-			score(cfg.clone(), pool.clone()).await?;
+		// park until the clock is running
+		if !matches!(*state_rx.borrow_and_update(), EngineState::Started { .. }) {
+			state_rx.changed().await?;
+			continue;
+		}
+
+		match timer
+			.transition(&pool, |inner| {
+				inner.poll();
+			})
+			.await
+		{
+			Ok(true) => info!("Competition clock ran out, scoring finished"),
+			Ok(false) => (),
+			Err(e) => {
+				error!("Failed to record the end of the competition: {:#}", e);
+				tokio::time::sleep(ROUND_WRITE_BACKOFF).await;
+				continue;
+			}
+		}
+
+		// polled on a copy, since the transition above already recorded any end
+		let remaining = {
+			let mut inner = *timer.read().await;
+			inner.poll()
+		};
+		let Some(remaining) = remaining else {
+			continue;
+		};
+
+		// scoring halts as soon as the competition clock runs out
+		let round = async {
+			if let Err(e) = score(cfg.clone(), pool.clone()).await {
+				error!("Scoring round failed: {:#}", e);
+			}
 			tokio::time::sleep(cfg.timing.jittered_interval()).await;
+		};
+
+		// any transition (pause, stop) drops the in-flight round, aborting its
+		// checks and rolling back anything it had not yet committed
+		tokio::select! {
+			_ = timeout(remaining.to_std()?, round) => (),
+			changed = state_rx.changed() => {
+				changed?;
+				info!("Engine state changed, aborting in-flight scoring round");
+			}
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn repeating_sla_every_limit_downs() {
+		let incurred: Vec<i64> = (0..=10)
+			.filter(|&downs| downs_incur_sla(SlaPolicy::Repeating, downs, 3))
+			.collect();
+		assert_eq!(incurred, [3, 6, 9]);
+	}
+
+	#[test]
+	fn one_shot_sla_once_per_outage() {
+		let incurred: Vec<i64> = (0..=10)
+			.filter(|&downs| downs_incur_sla(SlaPolicy::OneShot, downs, 3))
+			.collect();
+		assert_eq!(incurred, [3]);
+	}
+
+	#[test]
+	fn uptime_sla() {
+		let window = |checks, ups, penalized| UptimeWindow {
+			checks,
+			ups,
+			penalized,
+		};
+
+		// 70% over a full window of 10, below 80%
+		assert!(window_incurs_sla(&window(10, 7, false), 10, 80));
+		// exactly at the threshold
+		assert!(!window_incurs_sla(&window(10, 8, false), 10, 80));
+		// not yet a full window
+		assert!(!window_incurs_sla(&window(9, 0, false), 10, 80));
+		// already penalized within the window
+		assert!(!window_incurs_sla(&window(10, 7, true), 10, 80));
+	}
+}
@@ -1,9 +1,17 @@
+use crate::db::{self, models::EngineStateRecord};
+use anyhow::bail;
 use chrono::{DateTime, Duration, Utc};
+use log::error;
+use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, RwLock};
 
-#[derive(Clone, Copy, Debug)]
+// how often the running engine reports that it is alive
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub enum EngineState {
+	#[default]
 	Uninitialized,
 	Started {
 		time_started: DateTime<Utc>,
@@ -12,11 +20,55 @@ pub enum EngineState {
 	Stopped {
 		time_remaining: Duration,
 	},
+	Finished,
 }
 
-impl Default for EngineState {
-	fn default() -> Self {
-		Self::Uninitialized
+impl EngineState {
+	pub fn time_remaining(&self) -> Option<Duration> {
+		match *self {
+			Self::Started {
+				time_started,
+				time_remaining_at_start,
+			} => Some((time_remaining_at_start - (Utc::now() - time_started)).max(Duration::zero())),
+			Self::Stopped { time_remaining } => Some(time_remaining),
+			Self::Uninitialized | Self::Finished => None,
+		}
+	}
+
+	pub fn deadline(&self) -> Option<DateTime<Utc>> {
+		match *self {
+			Self::Started {
+				time_started,
+				time_remaining_at_start,
+			} => Some(time_started + time_remaining_at_start),
+			_ => None,
+		}
+	}
+}
+
+// a clock that was running when the engine went down resumes as paused, having
+// lost only the time up until the last heartbeat
+impl TryFrom<EngineStateRecord> for EngineState {
+	type Error = anyhow::Error;
+
+	fn try_from(record: EngineStateRecord) -> Result<Self, Self::Error> {
+		let time_remaining = record.time_remaining_ms.map(Duration::milliseconds);
+		Ok(
+			match (record.state.as_str(), record.time_started, time_remaining) {
+				("uninitialized", ..) => Self::Uninitialized,
+				("started", Some(time_started), Some(time_remaining_at_start)) => Self::Stopped {
+					time_remaining: (time_remaining_at_start - (record.last_seen - time_started))
+						.max(Duration::zero()),
+				},
+				("stopped", _, Some(time_remaining)) => Self::Stopped { time_remaining },
+				("finished", ..) => Self::Finished,
+				_ => bail!(
+					"malformed engine state '{}' recorded at {}",
+					record.state,
+					record.time
+				),
+			},
+		)
 	}
 }
 
@@ -27,7 +79,7 @@ pub struct TimerInner {
 }
 
 impl TimerInner {
-	fn start(&mut self) {
+	pub fn start(&mut self) {
 		use EngineState::*;
 		match self.engine_state {
 			Uninitialized => {
@@ -42,14 +94,106 @@ impl TimerInner {
 					time_remaining_at_start: time_remaining,
 				}
 			}
-			Started { .. } => (),
+			Started { .. } | Finished => (),
+		}
+	}
+
+	pub fn stop(&mut self) {
+		if matches!(self.engine_state, EngineState::Started { .. }) {
+			if let Some(time_remaining) = self.engine_state.time_remaining() {
+				self.engine_state = EngineState::Stopped { time_remaining };
+			}
+		}
+	}
+
+	// transitions to `Finished` once the competition clock runs out, returning
+	// the time left if scoring should continue
+	pub fn poll(&mut self) -> Option<Duration> {
+		if !matches!(self.engine_state, EngineState::Started { .. }) {
+			return None;
+		}
+
+		match self.engine_state.time_remaining() {
+			Some(remaining) if remaining > Duration::zero() => Some(remaining),
+			_ => {
+				self.engine_state = EngineState::Finished;
+				None
+			}
 		}
 	}
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Timer {
 	pub inner: Arc<RwLock<TimerInner>>,
+	// broadcasts every engine state transition to interested tasks
+	state_tx: Arc<watch::Sender<EngineState>>,
+}
+
+impl Timer {
+	pub fn new(competition_length: Duration, engine_state: EngineState) -> Self {
+		let (state_tx, _) = watch::channel(engine_state);
+		Self {
+			inner: Arc::new(RwLock::new(TimerInner {
+				competition_length,
+				engine_state,
+			})),
+			state_tx: Arc::new(state_tx),
+		}
+	}
+
+	pub fn subscribe(&self) -> watch::Receiver<EngineState> {
+		self.state_tx.subscribe()
+	}
+
+	// applies `transition` to the engine state, persisting the result before
+	// it takes effect so memory never runs ahead of the database, and
+	// broadcasting it; returns whether the state changed
+	pub async fn transition(
+		&self,
+		pool: &PgPool,
+		transition: impl FnOnce(&mut TimerInner),
+	) -> anyhow::Result<bool> {
+		let mut inner = self.write().await;
+		let mut next = *inner;
+		transition(&mut next);
+		if next.engine_state == inner.engine_state {
+			return Ok(false);
+		}
+
+		db::mutation::record_engine_state(pool, next.engine_state, Utc::now()).await?;
+		*inner = next;
+		self.state_tx.send_replace(next.engine_state);
+		Ok(true)
+	}
+
+	// restores the last recorded engine state, persisting the implicit pause if
+	// the engine went down while the clock was running
+	pub async fn restore(competition_length: Duration, pool: &PgPool) -> anyhow::Result<Self> {
+		let Some(record) = db::query::latest_engine_state(pool).await? else {
+			return Ok(Self::new(competition_length, EngineState::Uninitialized));
+		};
+
+		let was_started = record.state == "started";
+		let engine_state = EngineState::try_from(record)?;
+		if was_started {
+			db::mutation::record_engine_state(pool, engine_state, Utc::now()).await?;
+		}
+
+		Ok(Self::new(competition_length, engine_state))
+	}
+}
+
+pub async fn heartbeat(timer: Timer, pool: PgPool) {
+	let mut interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+	loop {
+		interval.tick().await;
+		if matches!(timer.read().await.engine_state, EngineState::Started { .. }) {
+			if let Err(e) = db::mutation::engine_heartbeat(&pool, Utc::now()).await {
+				error!("Failed to record engine heartbeat: {}", e);
+			}
+		}
+	}
 }
 
 impl std::ops::Deref for Timer {
@@ -59,3 +203,151 @@ impl std::ops::Deref for Timer {
 		&self.inner
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn record(
+		state: &str,
+		time_started: Option<DateTime<Utc>>,
+		time_remaining: Option<Duration>,
+		last_seen: DateTime<Utc>,
+	) -> EngineStateRecord {
+		EngineStateRecord {
+			state: state.to_owned(),
+			time_started,
+			time_remaining_ms: time_remaining.map(|remaining| remaining.num_milliseconds()),
+			time: time_started.unwrap_or(last_seen),
+			last_seen,
+		}
+	}
+
+	#[test]
+	fn running_clock_restores_paused_without_downtime() {
+		let time_started = Utc::now() - Duration::hours(3);
+		// last heard from an hour in, then down for two hours
+		let last_seen = time_started + Duration::hours(1);
+		let restored = EngineState::try_from(record(
+			"started",
+			Some(time_started),
+			Some(Duration::hours(4)),
+			last_seen,
+		))
+		.unwrap();
+		assert_eq!(
+			restored,
+			EngineState::Stopped {
+				time_remaining: Duration::hours(3)
+			}
+		);
+	}
+
+	#[test]
+	fn clock_that_ran_out_while_down_restores_with_none_left() {
+		let time_started = Utc::now() - Duration::hours(6);
+		let last_seen = time_started + Duration::hours(5);
+		let restored = EngineState::try_from(record(
+			"started",
+			Some(time_started),
+			Some(Duration::hours(4)),
+			last_seen,
+		))
+		.unwrap();
+		assert_eq!(
+			restored,
+			EngineState::Stopped {
+				time_remaining: Duration::zero()
+			}
+		);
+	}
+
+	#[test]
+	fn stopped_clock_restores_as_is() {
+		let restored = EngineState::try_from(record(
+			"stopped",
+			None,
+			Some(Duration::minutes(90)),
+			Utc::now(),
+		))
+		.unwrap();
+		assert_eq!(
+			restored,
+			EngineState::Stopped {
+				time_remaining: Duration::minutes(90)
+			}
+		);
+	}
+
+	#[test]
+	fn malformed_records_are_rejected() {
+		// a running clock without its start time
+		assert!(EngineState::try_from(record(
+			"started",
+			None,
+			Some(Duration::hours(1)),
+			Utc::now()
+		))
+		.is_err());
+		assert!(EngineState::try_from(record("stopped", None, None, Utc::now())).is_err());
+		assert!(EngineState::try_from(record("running", None, None, Utc::now())).is_err());
+	}
+
+	fn timer(engine_state: EngineState) -> TimerInner {
+		TimerInner {
+			competition_length: Duration::hours(4),
+			engine_state,
+		}
+	}
+
+	#[test]
+	fn poll_reports_time_left() {
+		let mut inner = timer(EngineState::Started {
+			time_started: Utc::now() - Duration::hours(1),
+			time_remaining_at_start: Duration::hours(4),
+		});
+		let remaining = inner.poll().unwrap();
+		assert!(
+			remaining <= Duration::hours(3)
+				&& remaining > Duration::hours(3) - Duration::minutes(1)
+		);
+		assert!(matches!(inner.engine_state, EngineState::Started { .. }));
+	}
+
+	#[test]
+	fn poll_finishes_an_expired_clock() {
+		let mut inner = timer(EngineState::Started {
+			time_started: Utc::now() - Duration::hours(5),
+			time_remaining_at_start: Duration::hours(4),
+		});
+		assert_eq!(inner.poll(), None);
+		assert_eq!(inner.engine_state, EngineState::Finished);
+	}
+
+	#[test]
+	fn poll_ignores_a_stopped_clock() {
+		let stopped = EngineState::Stopped {
+			time_remaining: Duration::hours(1),
+		};
+		let mut inner = timer(stopped);
+		assert_eq!(inner.poll(), None);
+		assert_eq!(inner.engine_state, stopped);
+	}
+
+	#[test]
+	fn stop_keeps_the_time_left() {
+		let mut inner = timer(EngineState::Started {
+			time_started: Utc::now() - Duration::hours(1),
+			time_remaining_at_start: Duration::hours(2),
+		});
+		inner.stop();
+		let EngineState::Stopped { time_remaining } = inner.engine_state else {
+			panic!("expected a stopped clock, got {:?}", inner.engine_state);
+		};
+		assert!(time_remaining <= Duration::hours(1) && time_remaining > Duration::minutes(59));
+
+		// stopping again changes nothing
+		inner.stop();
+		assert_eq!(inner.engine_state, EngineState::Stopped { time_remaining });
+	}
+}
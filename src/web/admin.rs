@@ -0,0 +1,128 @@
+use crate::{
+	auth::AuthSession,
+	get_base_template,
+	state::EngineState,
+	web::{BaseTemplate, WebCtxt, WebResult},
+};
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode, response::Redirect};
+use axum_messages::{Message, Messages};
+use log::info;
+
+#[derive(Template)]
+#[template(path = "admin.html")]
+struct AdminTemplate {
+	base: BaseTemplate,
+	messages: Vec<Message>,
+}
+
+fn is_admin(auth_session: &AuthSession) -> bool {
+	auth_session.user.as_ref().is_some_and(|user| user.admin)
+}
+
+pub async fn get(
+	State(ctxt): State<WebCtxt>,
+	auth_session: AuthSession,
+	messages: Messages,
+) -> impl IntoResponse {
+	if is_admin(&auth_session) {
+		AdminTemplate {
+			base: get_base_template!(ctxt, auth_session),
+			messages: messages.into_iter().collect(),
+		}
+		.into_response()
+	} else {
+		StatusCode::FORBIDDEN.into_response()
+	}
+}
+
+pub async fn start(
+	State(ctxt): State<WebCtxt>,
+	auth_session: AuthSession,
+	messages: Messages,
+) -> WebResult<Response> {
+	if !is_admin(&auth_session) {
+		return Ok(StatusCode::FORBIDDEN.into_response());
+	}
+
+	let transitioned = ctxt
+		.timer
+		.transition(&ctxt.pool, |timer| {
+			if matches!(timer.engine_state, EngineState::Uninitialized) {
+				timer.start();
+			}
+		})
+		.await?;
+	if transitioned {
+		info!(
+			"Competition started by '{}'",
+			auth_session.user.unwrap().username
+		);
+		messages.success("competition started");
+	} else {
+		messages.error("competition has already been started");
+	}
+
+	Ok(Redirect::to("/admin").into_response())
+}
+
+pub async fn pause(
+	State(ctxt): State<WebCtxt>,
+	auth_session: AuthSession,
+	messages: Messages,
+) -> WebResult<Response> {
+	if !is_admin(&auth_session) {
+		return Ok(StatusCode::FORBIDDEN.into_response());
+	}
+
+	let transitioned = ctxt
+		.timer
+		.transition(&ctxt.pool, |timer| {
+			if matches!(timer.engine_state, EngineState::Started { .. }) {
+				timer.stop();
+			}
+		})
+		.await?;
+	if transitioned {
+		info!(
+			"Competition paused by '{}'",
+			auth_session.user.unwrap().username
+		);
+		messages.success("competition paused");
+	} else {
+		messages.error("competition is not running");
+	}
+
+	Ok(Redirect::to("/admin").into_response())
+}
+
+pub async fn resume(
+	State(ctxt): State<WebCtxt>,
+	auth_session: AuthSession,
+	messages: Messages,
+) -> WebResult<Response> {
+	if !is_admin(&auth_session) {
+		return Ok(StatusCode::FORBIDDEN.into_response());
+	}
+
+	let transitioned = ctxt
+		.timer
+		.transition(&ctxt.pool, |timer| {
+			if matches!(timer.engine_state, EngineState::Stopped { .. }) {
+				timer.start();
+			}
+		})
+		.await?;
+	if transitioned {
+		info!(
+			"Competition resumed by '{}'",
+			auth_session.user.unwrap().username
+		);
+		messages.success("competition resumed");
+	} else {
+		messages.error("competition is not paused");
+	}
+
+	Ok(Redirect::to("/admin").into_response())
+}
@@ -1,10 +1,13 @@
+mod admin;
 mod injects;
 mod login;
 mod logout;
+mod pcr;
 mod status;
 
 use crate::{
 	config::Config,
+	offset::Offset,
 	state::{EngineState, Timer},
 };
 use axum::{
@@ -60,6 +63,26 @@ pub struct BaseTemplate {
 	pub user: Option<<Config as AuthnBackend>::User>,
 }
 
+impl BaseTemplate {
+	pub fn is_admin(&self) -> bool {
+		self.user.as_ref().is_some_and(|user| user.admin)
+	}
+
+	// time remaining in the competition, formatted as HH:MM:SS
+	pub fn countdown(&self) -> Option<String> {
+		self.engine_state
+			.time_remaining()
+			.map(|remaining| Offset::from(remaining).to_string())
+	}
+
+	// deadline in unix millis for the live countdown, if the clock is running
+	pub fn deadline_millis(&self) -> Option<i64> {
+		self.engine_state
+			.deadline()
+			.map(|deadline| deadline.timestamp_millis())
+	}
+}
+
 #[macro_export]
 macro_rules! get_base_template {
 	($ctxt:ident, $auth_session:ident) => {
@@ -90,6 +113,12 @@ pub async fn run(config: Config, timer: Timer, pool: PgPool) -> anyhow::Result<(
 	let protected = Router::new()
 		.route("/injects", get(injects::get))
 		.route("/injects/:inject_number", get(injects::page::get))
+		.route("/pcr", get(pcr::get))
+		.route("/pcr", post(pcr::post))
+		.route("/admin", get(admin::get))
+		.route("/admin/start", post(admin::start))
+		.route("/admin/pause", post(admin::pause))
+		.route("/admin/resume", post(admin::resume))
 		.route_layer(login_required!(Config, login_url = "/login"))
 		.route("/login", get(login::get))
 		.route("/login", post(login::post))
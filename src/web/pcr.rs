@@ -0,0 +1,130 @@
+use crate::{
+	auth::AuthSession,
+	db,
+	db::models::PasswordChange,
+	get_base_template,
+	state::EngineState,
+	web::{BaseTemplate, WebCtxt, WebResult},
+};
+use askama::Template;
+use askama_axum::{IntoResponse, Response};
+use axum::{extract::State, http::StatusCode, response::Redirect, Form};
+use axum_messages::{Message, Messages};
+use chrono::Utc;
+use itertools::Itertools;
+use log::info;
+use serde::Deserialize;
+
+// a service whose password can be changed, as (vm, service, user)
+type Credentialed<'a> = (&'a str, &'a str, &'a str);
+
+#[derive(Template)]
+#[template(path = "pcr.html")]
+struct PcrTemplate<'a> {
+	base: BaseTemplate,
+	messages: Vec<Message>,
+	services: Vec<Credentialed<'a>>,
+	changes: Vec<PasswordChange>,
+}
+
+#[derive(Deserialize)]
+pub struct PcrForm {
+	// formatted as `vm/service`
+	service: String,
+	password: String,
+}
+
+fn credentialed_services(ctxt: &WebCtxt) -> Vec<Credentialed<'_>> {
+	ctxt.config
+		.vms
+		.iter()
+		.flat_map(|(vm_alias, vm)| {
+			vm.services
+				.iter()
+				.filter_map(move |(service_alias, service)| {
+					service
+						.check
+						.credentialed_user()
+						.map(|user| (vm_alias.as_str(), service_alias.as_str(), user))
+				})
+		})
+		.sorted()
+		.collect()
+}
+
+// PCRs are submitted on behalf of a team, so admins have none
+fn team(auth_session: &AuthSession) -> Option<&str> {
+	auth_session
+		.user
+		.as_ref()
+		.filter(|user| !user.admin)
+		.map(|user| user.username.as_str())
+}
+
+pub async fn get(
+	State(ctxt): State<WebCtxt>,
+	auth_session: AuthSession,
+	messages: Messages,
+) -> WebResult<Response> {
+	let Some(team) = team(&auth_session) else {
+		return Ok(StatusCode::FORBIDDEN.into_response());
+	};
+
+	let changes = db::query::team_password_changes(&ctxt.pool, team).await?;
+	Ok(PcrTemplate {
+		services: credentialed_services(&ctxt),
+		changes,
+		messages: messages.into_iter().collect(),
+		base: get_base_template!(ctxt, auth_session),
+	}
+	.into_response())
+}
+
+pub async fn post(
+	State(ctxt): State<WebCtxt>,
+	auth_session: AuthSession,
+	messages: Messages,
+	Form(pcr): Form<PcrForm>,
+) -> WebResult<Response> {
+	let Some(team) = team(&auth_session) else {
+		return Ok(StatusCode::FORBIDDEN.into_response());
+	};
+
+	let services = credentialed_services(&ctxt);
+	let service = pcr.service.split_once('/').and_then(|(vm, service)| {
+		services
+			.iter()
+			.find(|(vm_alias, service_alias, _)| *vm_alias == vm && *service_alias == service)
+	});
+
+	match service {
+		None => {
+			messages.error("unknown service");
+		}
+		Some(_) if pcr.password.is_empty() => {
+			messages.error("password cannot be empty");
+		}
+		Some((vm, service, user)) => {
+			db::mutation::record_password_change(
+				&ctxt.pool,
+				team,
+				vm,
+				service,
+				user,
+				&pcr.password,
+				Utc::now(),
+			)
+			.await?;
+			info!(
+				"Password for user='{}' changed by team='{}' on vm='{}', service='{}'",
+				user, team, vm, service
+			);
+			messages.success(format!(
+				"password for '{}' on {}/{} changed",
+				user, vm, service
+			));
+		}
+	}
+
+	Ok(Redirect::to("/pcr").into_response())
+}
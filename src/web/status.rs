@@ -30,6 +30,7 @@ struct StatusTemplate {
 	status_table: FlattenedServices,
 	vm_service_names: Vec<String>,
 	latest_time: String,
+	latest_round: Option<i32>,
 	team_table: Vec<TeamInfo>,
 	team_progressions: Vec<TeamProgression>,
 }
@@ -84,7 +85,7 @@ fn extract_team_table(snapshots: &[LatestTeamSnapshot]) -> Vec<TeamInfo> {
 				team: team.team.clone(),
 				services_up,
 				services_down,
-				points: team.points,
+				points: team.total_points,
 			}
 		})
 		.sorted_by_key(|team| team.points)
@@ -101,6 +102,7 @@ pub async fn get(
 		DateTime::<Local>::from(teams.iter().map(|t| t.time).max().unwrap_or_default())
 			.format("%m/%d/%Y %H:%M %p")
 			.to_string();
+	let latest_round = teams.iter().filter_map(|t| t.round).max();
 	let (status_table, vm_service_names) = flatten_team_snapshots(&teams);
 	let team_table = extract_team_table(&teams);
 	let team_progressions = db::query::team_progressions(&ctxt.pool).await?;
@@ -110,6 +112,7 @@ pub async fn get(
 		status_table,
 		vm_service_names,
 		latest_time,
+		latest_round,
 		team_table,
 		team_progressions,
 	})